@@ -0,0 +1,36 @@
+use axum::{
+    extract::{rejection::JsonRejection, FromRequest},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use crt_core::error::{ApiError, ApiErrorBody};
+
+/// Axum-facing wrapper so `ApiError` from `crt-core` can be returned from handlers.
+#[derive(Debug)]
+pub struct AppError(pub ApiError);
+
+impl From<ApiError> for AppError {
+    fn from(error: ApiError) -> Self {
+        AppError(error)
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        AppError(ApiError::InvalidBody {
+            message: rejection.body_text(),
+        })
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.0.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (status, Json(ApiErrorBody::from(self.0))).into_response()
+    }
+}
+
+/// JSON extractor whose rejections are reported as `ApiError::InvalidBody`.
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(AppError))]
+pub struct ApiJson<T>(pub T);
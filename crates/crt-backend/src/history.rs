@@ -0,0 +1,124 @@
+use chrono::NaiveDate;
+use crt_core::{
+    error::ApiError,
+    types::{DoraMetric, MetricHistory, MetricSeries},
+};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tokio::sync::RwLock;
+
+/// Team used when a request does not name one.
+pub const DEFAULT_TEAM: &str = "default";
+
+/// Metric history per team, kept in memory and written back to a JSON file
+/// after every change when a path is configured.
+pub struct HistoryStore {
+    path: Option<PathBuf>,
+    teams: RwLock<BTreeMap<String, MetricHistory>>,
+}
+
+impl HistoryStore {
+    /// Open the store at `path`, starting empty when the file does not exist yet.
+    pub fn open(path: Option<PathBuf>) -> anyhow::Result<Self> {
+        let teams = match &path {
+            Some(path) if path.exists() => serde_json::from_str(&std::fs::read_to_string(path)?)?,
+            _ => BTreeMap::new(),
+        };
+        Ok(HistoryStore {
+            path,
+            teams: RwLock::new(teams),
+        })
+    }
+
+    pub async fn team(&self, team: &str) -> MetricHistory {
+        self.teams.read().await.get(team).cloned().unwrap_or_default()
+    }
+
+    /// Record `metrics` for `date`, replacing observations already stored for
+    /// that day. New series take the unit of their first observation. Either
+    /// every metric is recorded or, on the first that cannot be, none is.
+    pub async fn record<'a>(
+        &self,
+        team: &str,
+        date: NaiveDate,
+        metrics: impl IntoIterator<Item = (&'a String, &'a DoraMetric)>,
+    ) -> Result<MetricHistory, ApiError> {
+        let mut teams = self.teams.write().await;
+        let mut history = teams.get(team).cloned().unwrap_or_default();
+        for (name, metric) in metrics {
+            history
+                .entry(name.clone())
+                .or_insert_with(|| MetricSeries::new(metric.unit.clone()))
+                .record_metric(date, metric)
+                .map_err(|message| ApiError::validation(format!("metrics.{name}"), message))?;
+        }
+
+        let previous = teams.insert(team.to_string(), history.clone());
+        if let Err(error) = self.persist(&teams).await {
+            match previous {
+                Some(previous) => teams.insert(team.to_string(), previous),
+                None => teams.remove(team),
+            };
+            return Err(error);
+        }
+        Ok(history)
+    }
+
+    /// Write `teams` to a file beside the store and rename it over the store,
+    /// so a failed write never leaves it half written.
+    async fn persist(&self, teams: &BTreeMap<String, MetricHistory>) -> Result<(), ApiError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(teams).map_err(ApiError::internal)?;
+        let mut temp = path.clone().into_os_string();
+        temp.push(".tmp");
+        tokio::fs::write(&temp, json).await.map_err(ApiError::internal)?;
+        tokio::fs::rename(&temp, path).await.map_err(ApiError::internal)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metric(value: f32, unit: &str) -> DoraMetric {
+        DoraMetric {
+            value,
+            unit: unit.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_metric_leaves_history_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        let store = HistoryStore::open(Some(path.clone())).unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+
+        let first = BTreeMap::from([("lead_time".to_string(), metric(2.0, "days"))]);
+        store.record("payments", day(1), &first).await.unwrap();
+
+        // the lead time converts, but comes before an mttr series that cannot
+        let mttr = BTreeMap::from([("mttr".to_string(), metric(1.0, "days"))]);
+        store.record("payments", day(1), &mttr).await.unwrap();
+        let second = BTreeMap::from([
+            ("lead_time".to_string(), metric(36.0, "hours")),
+            ("mttr".to_string(), metric(4.0, "%")),
+        ]);
+        let error = store.record("payments", day(2), &second).await.unwrap_err();
+        assert_eq!(error.field(), Some("metrics.mttr"));
+
+        let history = store.team("payments").await;
+        assert_eq!(history["lead_time"].observations.len(), 1);
+
+        // a later success must not persist the rejected lead time either
+        let third = BTreeMap::from([("mttr".to_string(), metric(12.0, "hours"))]);
+        store.record("payments", day(3), &third).await.unwrap();
+        let reopened = HistoryStore::open(Some(path)).unwrap().team("payments").await;
+        assert_eq!(reopened["lead_time"].observations.len(), 1);
+        assert_eq!(reopened["mttr"].observations.len(), 2);
+        assert!(!dir.path().join("history.json.tmp").exists());
+    }
+}
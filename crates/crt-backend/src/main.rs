@@ -1,10 +1,11 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{Query, State},
     response::Json,
     routing::{get, post},
     Router,
 };
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tower::ServiceBuilder;
@@ -13,14 +14,34 @@ use tower_http::trace::TraceLayer;
 use tracing::{info, warn, trace};
 
 use crt_core::{
+    error::ApiError,
     types::*,
-    validation::Validate,
+    catalogue::MetricCatalogue,
+    cloud::{core_root_cause, parse_cloud, EvaporatingCloud},
+    cutsets::{minimal_cut_sets, CutSetLimits},
+    frt::{check_frt, parse_frt, FrtCheck},
+    negation::negate_crt,
+    validation::{Validate, ValidationContext, ValidationLimits},
     dora::*,
+    evidence::{build_evidence_map, EvidenceMap},
+    graph::{crt_udes, CausalGraph},
+    leverage::rank_leverage,
+    parser::{parse_crt, CRT},
+    trend::{compute_trends, merge_history, MetricTrend},
+    westrum::{self, WestrumCulture, WestrumSummary},
 };
 
+mod error;
+mod history;
+
+use error::{ApiJson, AppError};
+use history::{HistoryStore, DEFAULT_TEAM};
+
 #[derive(Clone)]
 struct AppState {
     agent_base_url: String,
+    validation: ValidationContext,
+    history: Arc<HistoryStore>,
 }
 
 #[tokio::main]
@@ -31,7 +52,14 @@ async fn main() -> anyhow::Result<()> {
     let agent_base_url = std::env::var("AGENT_BASE_URL")
         .unwrap_or_else(|_| "http://localhost:3000".to_string());
 
-    let state = AppState { agent_base_url };
+    let state = AppState {
+        agent_base_url,
+        validation: ValidationContext {
+            limits: limits_from_env(),
+            catalogue: Arc::new(catalogue_from_env()?),
+        },
+        history: Arc::new(HistoryStore::open(std::env::var("CRT_HISTORY_PATH").ok().map(Into::into))?),
+    };
 
     // Build our application with routes
     let app = Router::new()
@@ -40,6 +68,11 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/refine", post(refine))
         .route("/api/evaluate_analysis", post(evaluate_analysis))
         .route("/api/analyse_with_feedback", post(analyse_with_feedback))
+        .route("/api/plan", post(plan))
+        .route("/api/cloud", post(cloud))
+        .route("/api/frt/check", post(check_future_reality_tree))
+        .route("/api/frt/negate", post(negate))
+        .route("/api/metrics/history", get(metrics_history).post(record_metrics_history))
         .layer(
             ServiceBuilder::new()
                 .layer(TraceLayer::new_for_http())
@@ -56,28 +89,145 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+fn limits_from_env() -> ValidationLimits {
+    let read = |name: &str, default: usize| {
+        std::env::var(name)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    };
+    let defaults = ValidationLimits::default();
+    ValidationLimits {
+        max_entities: read("CRT_MAX_ENTITIES", defaults.max_entities),
+        max_links: read("CRT_MAX_LINKS", defaults.max_links),
+        max_entity_text_len: read("CRT_MAX_ENTITY_TEXT_LEN", defaults.max_entity_text_len),
+    }
+}
+
+/// The metric catalogue named by `CRT_METRIC_CATALOGUE` (TOML or JSON), or
+/// the built-in one when unset.
+fn catalogue_from_env() -> anyhow::Result<MetricCatalogue> {
+    match std::env::var("CRT_METRIC_CATALOGUE") {
+        Ok(path) => {
+            let catalogue = MetricCatalogue::load(std::path::Path::new(&path))?;
+            info!("loaded {} metrics from {}", catalogue.metrics.len(), path);
+            Ok(catalogue)
+        }
+        Err(_) => Ok(MetricCatalogue::builtin()),
+    }
+}
+
+/// Westrum score and survey summary to send to the agents.
+fn westrum_inputs(request: &AnalyseRequest) -> (Option<f32>, Option<WestrumSummary>) {
+    let summary = request.westrum_survey.as_ref().and_then(|survey| survey.aggregate());
+    (westrum::effective_score(request.westrum, summary.as_ref()), summary)
+}
+
+/// Translated DORA metrics for the agents, together with their benchmark tiers.
+fn dora_inputs(catalogue: &MetricCatalogue, request: &AnalyseRequest) -> (HashMap<String, DoraMetric>, DoraProfile) {
+    let metrics = translate_dora_metrics_for_agent(catalogue, &request.dora_metrics);
+    let tiers = classify_dora_metrics(&metrics, resolve_dora_benchmark(request.dora_benchmark.as_deref()));
+    (metrics, tiers)
+}
+
+/// The request's CRT, already validated, for the structure sent alongside it.
+fn parsed_crt(crt: &str) -> Result<CRT, AppError> {
+    Ok(parse_crt(crt).map_err(ApiError::internal)?)
+}
+
+/// Evidence for the metric-tagged entities of the CRT, from every metric sent
+/// to the agents.
+fn evidence_inputs(
+    catalogue: &MetricCatalogue,
+    crt: &CRT,
+    tiers: &DoraProfile,
+    metric_maps: [&HashMap<String, DoraMetric>; 3],
+) -> EvidenceMap {
+    let metrics: HashMap<String, DoraMetric> = metric_maps
+        .into_iter()
+        .flat_map(|map| map.iter().map(|(name, metric)| (name.clone(), metric.clone())))
+        .collect();
+    build_evidence_map(crt, &metrics, tiers, catalogue)
+}
+
+fn team_name(team: Option<&str>) -> &str {
+    team.unwrap_or(DEFAULT_TEAM)
+}
+
+/// Trend summaries for the agents when the request asks for them, over the
+/// stored history of its team overlaid with the history it carries.
+async fn trend_inputs(state: &AppState, request: &AnalyseRequest) -> Option<Vec<MetricTrend>> {
+    if !request.include_trends {
+        return None;
+    }
+    let mut history = state.history.team(team_name(request.team.as_deref())).await;
+    merge_history(&mut history, &request.history);
+    Some(compute_trends(&state.validation.catalogue, &history))
+}
+
+fn history_response(state: &AppState, team: &str, history: MetricHistory) -> MetricHistoryResponse {
+    MetricHistoryResponse {
+        team: team.to_string(),
+        trends: compute_trends(&state.validation.catalogue, &history),
+        history,
+    }
+}
+
 async fn root() -> &'static str {
     "CRT Backend API"
 }
 
 async fn analyse(
     State(state): State<AppState>,
-    Json(request): Json<AnalyseRequest>,
-) -> Result<Json<AnalysisResponse>, (StatusCode, String)> {
+    ApiJson(request): ApiJson<AnalyseRequest>,
+) -> Result<Json<AnalysisResponse>, AppError> {
     // Validate request first
-    request.validate().map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    request.validate_all_with(&state.validation)?;
+
+    let (westrum, westrum_survey) = westrum_inputs(&request);
+    let (dora_metrics, dora_tiers) = dora_inputs(&state.validation.catalogue, &request);
+    let extended_engineering_metrics = translate_engineering_metrics_for_agent(&state.validation.catalogue, &request.extended_engineering_metrics);
+    let metrics = translate_metrics_for_agent(&state.validation.catalogue, &request.metrics);
+
+    let tree = parsed_crt(&request.crt)?;
+    // Keep today's snapshot of a named team so later analyses can report
+    // trends; the analysis does not depend on it, so a failed write only warns
+    if let Some(team) = request.team.as_deref() {
+        let today = chrono::Utc::now().date_naive();
+        let snapshot = dora_metrics.iter().chain(&extended_engineering_metrics).chain(&metrics);
+        if let Err(err) = state.history.record(team, today, snapshot).await {
+            warn!(?err, team, "Could not record the metric history snapshot");
+        }
+    }
+    let trends = trend_inputs(&state, &request).await;
+    let evidence = evidence_inputs(
+        &state.validation.catalogue,
+        &tree,
+        &dora_tiers,
+        [&dora_metrics, &extended_engineering_metrics, &metrics],
+    );
 
     // Translate metrics for agent consumption
     let agent_payload = AnalyserRequest {
         crt: request.crt,
-        dora_metrics: translate_dora_metrics_for_agent(&request.dora_metrics),
-        extended_engineering_metrics: translate_engineering_metrics_for_agent(&request.extended_engineering_metrics),
-        westrum: Some(request.westrum),
+        dora_metrics,
+        extended_engineering_metrics,
+        metrics,
+        westrum,
+        westrum_culture: westrum.and_then(WestrumCulture::from_score),
+        westrum_survey,
+        dora_tiers,
+        evidence,
+        entity_attributes: tree.entity_attributes(),
+        link_annotations: tree.link_annotations(),
+        causal_paths: CausalGraph::from_crt(&tree).strongest_paths_to_sinks(),
+        leverage: rank_leverage(&tree),
+        trends,
         time_allocation: request.time_allocation,
     };
 
     let body = serde_json::to_string(&agent_payload)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(ApiError::internal)?;
 
     let AgentResponse {
         output_text: analyser_text,
@@ -149,17 +299,17 @@ async fn analyse(
 
 async fn refine(
     State(state): State<AppState>,
-    Json(request): Json<RefineRequest>,
-) -> Result<Json<RefineResponse>, (StatusCode, String)> {
+    ApiJson(request): ApiJson<RefineRequest>,
+) -> Result<Json<RefineResponse>, AppError> {
     // Validate request first
-    request.validate().map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    request.validate_all_with(&state.validation)?;
 
     let goldratt_request = GoldrattRequest {
         message: request.content,
     };
     
     let body = serde_json::to_string(&goldratt_request)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(ApiError::internal)?;
 
     let AgentResponse {
         output_text: refinement,
@@ -180,23 +330,46 @@ async fn refine(
 
 async fn evaluate_analysis(
     State(state): State<AppState>,
-    Json(request): Json<EvaluateRequest>,
-) -> Result<Json<EvaluationResponse>, (StatusCode, String)> {
+    ApiJson(request): ApiJson<EvaluateRequest>,
+) -> Result<Json<EvaluationResponse>, AppError> {
     // Validate request first
-    request.validate().map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    request.validate_all_with(&state.validation)?;
+
+    let (westrum, westrum_survey) = westrum_inputs(&request.original_payload);
+    let (dora_metrics, dora_tiers) = dora_inputs(&state.validation.catalogue, &request.original_payload);
+    let extended_engineering_metrics = translate_engineering_metrics_for_agent(&state.validation.catalogue, &request.original_payload.extended_engineering_metrics);
+    let metrics = translate_metrics_for_agent(&state.validation.catalogue, &request.original_payload.metrics);
+    let trends = trend_inputs(&state, &request.original_payload).await;
+    let tree = parsed_crt(&request.original_payload.crt)?;
+    let evidence = evidence_inputs(
+        &state.validation.catalogue,
+        &tree,
+        &dora_tiers,
+        [&dora_metrics, &extended_engineering_metrics, &metrics],
+    );
 
     // Create a flattened payload for the evaluator
     let evaluator_payload = EvaluatorRequest {
         current_reality_tree: request.original_payload.crt,
-        dora_metrics: translate_dora_metrics_for_agent(&request.original_payload.dora_metrics),
-        extended_engineering_metrics: translate_engineering_metrics_for_agent(&request.original_payload.extended_engineering_metrics),
-        westrum_score: Some(request.original_payload.westrum),
+        dora_metrics,
+        extended_engineering_metrics,
+        metrics,
+        westrum_score: westrum,
+        westrum_culture: westrum.and_then(WestrumCulture::from_score),
+        westrum_survey,
+        dora_tiers,
+        evidence,
+        entity_attributes: tree.entity_attributes(),
+        link_annotations: tree.link_annotations(),
+        causal_paths: CausalGraph::from_crt(&tree).strongest_paths_to_sinks(),
+        leverage: rank_leverage(&tree),
+        trends,
         time_allocation: request.original_payload.time_allocation,
         analysis_result: request.analysis_result,
     };
 
     let body = serde_json::to_string(&evaluator_payload)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(ApiError::internal)?;
 
     let AgentResponse {
         output_text: evaluator_text,
@@ -327,24 +500,47 @@ async fn evaluate_analysis(
 
 async fn analyse_with_feedback(
     State(state): State<AppState>,
-    Json(request): Json<AnalyseWithFeedbackRequest>,
-) -> Result<Json<AnalysisResponse>, (StatusCode, String)> {
+    ApiJson(request): ApiJson<AnalyseWithFeedbackRequest>,
+) -> Result<Json<AnalysisResponse>, AppError> {
     // Validate request first
-    request.validate().map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    request.validate_all_with(&state.validation)?;
+
+    let (westrum, westrum_survey) = westrum_inputs(&request.original_payload);
+    let (dora_metrics, dora_tiers) = dora_inputs(&state.validation.catalogue, &request.original_payload);
+    let extended_engineering_metrics = translate_engineering_metrics_for_agent(&state.validation.catalogue, &request.original_payload.extended_engineering_metrics);
+    let metrics = translate_metrics_for_agent(&state.validation.catalogue, &request.original_payload.metrics);
+    let trends = trend_inputs(&state, &request.original_payload).await;
+    let tree = parsed_crt(&request.original_payload.crt)?;
+    let evidence = evidence_inputs(
+        &state.validation.catalogue,
+        &tree,
+        &dora_tiers,
+        [&dora_metrics, &extended_engineering_metrics, &metrics],
+    );
 
     // Translate metrics for agent consumption
     let agent_payload = AnalyserWithFeedbackRequest {
         crt: request.original_payload.crt,
-        dora_metrics: translate_dora_metrics_for_agent(&request.original_payload.dora_metrics),
-        extended_engineering_metrics: translate_engineering_metrics_for_agent(&request.original_payload.extended_engineering_metrics),
-        westrum: Some(request.original_payload.westrum),
+        dora_metrics,
+        extended_engineering_metrics,
+        metrics,
+        westrum,
+        westrum_culture: westrum.and_then(WestrumCulture::from_score),
+        westrum_survey,
+        dora_tiers,
+        evidence,
+        entity_attributes: tree.entity_attributes(),
+        link_annotations: tree.link_annotations(),
+        causal_paths: CausalGraph::from_crt(&tree).strongest_paths_to_sinks(),
+        leverage: rank_leverage(&tree),
+        trends,
         time_allocation: request.original_payload.time_allocation,
         analysis_result: request.analysis_result,
         evaluation: request.evaluation,
     };
 
     let body = serde_json::to_string(&agent_payload)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(ApiError::internal)?;
 
     let AgentResponse {
         output_text: analyser_text,
@@ -414,6 +610,153 @@ async fn analyse_with_feedback(
     Ok(Json(response))
 }
 
+#[derive(Debug, serde::Deserialize)]
+struct HistoryQuery {
+    team: Option<String>,
+}
+
+async fn plan(
+    State(state): State<AppState>,
+    ApiJson(request): ApiJson<PlanRequest>,
+) -> Result<Json<PlanResponse>, AppError> {
+    request.validate_all_with(&state.validation)?;
+
+    let (westrum, _) = westrum_inputs(&request.original_payload);
+    let (dora_metrics, _) = dora_inputs(&state.validation.catalogue, &request.original_payload);
+    let extended_engineering_metrics = translate_engineering_metrics_for_agent(&state.validation.catalogue, &request.original_payload.extended_engineering_metrics);
+    let metrics = translate_metrics_for_agent(&state.validation.catalogue, &request.original_payload.metrics);
+    let tree = parsed_crt(&request.original_payload.crt)?;
+    let cut_sets = crt_udes(&tree)
+        .into_iter()
+        .map(|ude| minimal_cut_sets(&tree, ude, CutSetLimits::default()))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map_err(ApiError::internal)?;
+
+    let planner_payload = PlannerRequest {
+        current_reality_tree: request.original_payload.crt,
+        dora_metrics,
+        extended_engineering_metrics,
+        metrics,
+        westrum_score: westrum,
+        time_allocation: request.original_payload.time_allocation,
+        analysis_result: request.analysis_result,
+        cut_sets,
+    };
+
+    let body = serde_json::to_string(&planner_payload)
+        .map_err(ApiError::internal)?;
+
+    let AgentResponse { output_text, run_id } = call_agent(&state, "planner", &body).await?;
+
+    let plan = match serde_json::from_str::<PlannerOutput>(&output_text) {
+        Ok(plan) => Some(plan),
+        Err(err) => {
+            warn!(?err, "Planner output was not valid plan JSON");
+            None
+        }
+    };
+
+    Ok(Json(PlanResponse {
+        run_id,
+        plan,
+        output_text,
+    }))
+}
+
+async fn cloud(
+    State(state): State<AppState>,
+    ApiJson(request): ApiJson<CloudRequest>,
+) -> Result<Json<CloudResponse>, AppError> {
+    request.validate_all_with(&state.validation)?;
+
+    let tree = parsed_crt(&request.crt)?;
+    let root_cause = request
+        .root_cause
+        .or_else(|| core_root_cause(&tree))
+        .ok_or_else(|| ApiError::validation("root_cause", "CRT has no root cause to build a cloud from"))?;
+    let draft = match &request.cloud {
+        Some(cloud) => parse_cloud(cloud).map_err(ApiError::internal)?,
+        None => EvaporatingCloud::from_root_cause(&tree, root_cause).unwrap_or_default(),
+    };
+
+    let agent_payload = CloudAgentRequest {
+        root_cause,
+        root_cause_text: tree.entities[&root_cause].text.clone(),
+        effects: CausalGraph::from_crt(&tree).effects_of(root_cause),
+        crt: request.crt,
+        cloud: draft,
+    };
+
+    let body = serde_json::to_string(&agent_payload)
+        .map_err(ApiError::internal)?;
+
+    let AgentResponse { output_text, run_id } = call_agent(&state, "cloud", &body).await?;
+
+    // The agent may answer with the cloud as JSON or as a cloud document
+    let cloud = serde_json::from_str::<EvaporatingCloud>(&output_text)
+        .ok()
+        .or_else(|| match parse_cloud(&output_text) {
+            Ok(cloud) => Some(cloud),
+            Err(err) => {
+                warn!(?err, "Cloud output was neither cloud JSON nor a cloud document");
+                None
+            }
+        });
+
+    Ok(Json(CloudResponse {
+        run_id,
+        root_cause,
+        document: cloud.as_ref().map(ToString::to_string),
+        cloud,
+        output_text,
+    }))
+}
+
+async fn check_future_reality_tree(
+    State(state): State<AppState>,
+    ApiJson(request): ApiJson<FrtCheckRequest>,
+) -> Result<Json<FrtCheck>, AppError> {
+    request.validate_all_with(&state.validation)?;
+
+    let tree = parsed_crt(&request.crt)?;
+    let frt = parse_frt(&request.frt).map_err(ApiError::internal)?;
+    Ok(Json(check_frt(&frt, &tree)))
+}
+
+async fn negate(
+    State(state): State<AppState>,
+    ApiJson(request): ApiJson<NegateRequest>,
+) -> Result<Json<NegateResponse>, AppError> {
+    request.validate_all_with(&state.validation)?;
+
+    let tree = parsed_crt(&request.crt)?;
+    let skeleton = negate_crt(&tree, &request.injected).map_err(ApiError::internal)?;
+    Ok(Json(NegateResponse {
+        document: skeleton.frt.to_string(),
+        skeleton,
+    }))
+}
+
+async fn metrics_history(
+    State(state): State<AppState>,
+    Query(query): Query<HistoryQuery>,
+) -> Json<MetricHistoryResponse> {
+    let team = team_name(query.team.as_deref());
+    let history = state.history.team(team).await;
+    Json(history_response(&state, team, history))
+}
+
+async fn record_metrics_history(
+    State(state): State<AppState>,
+    ApiJson(request): ApiJson<MetricHistoryUpdate>,
+) -> Result<Json<MetricHistoryResponse>, AppError> {
+    request.validate_all_with(&state.validation)?;
+
+    let team = team_name(request.team.as_deref());
+    let history = state.history.record(team, request.date, &request.metrics).await?;
+    Ok(Json(history_response(&state, team, history)))
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct AgentResponse {
     output_text: String,
@@ -424,13 +767,13 @@ async fn call_agent(
     state: &AppState,
     agent_name: &str,
     message: &str,
-) -> Result<AgentResponse, (StatusCode, String)> {
+) -> Result<AgentResponse, AppError> {
     let client = reqwest::Client::new();
     let url = format!("{}/agents/{}/run", state.agent_base_url, agent_name);
     let agent_request = GoldrattRequest {
         message: message.to_string(),
     };
-    let body = serde_json::to_string(&agent_request).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let body = serde_json::to_string(&agent_request).map_err(ApiError::internal)?;
     trace!("Calling {} with body {}", url, body);
     let response = client
         .post(&url)
@@ -438,7 +781,10 @@ async fn call_agent(
         .body(body)
         .send()
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| ApiError::AgentUnavailable {
+            agent: agent_name.to_string(),
+            message: e.to_string(),
+        })?;
 
     if !response.status().is_success() {
         let status = response.status();
@@ -447,13 +793,32 @@ async fn call_agent(
             .await
             .unwrap_or_else(|_| "Unknown error".to_string());
         trace!("Error calling {}: {}", url, error_text);
-        return Err((axum::http::StatusCode::from_u16(status.as_u16()).unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR), error_text));
+        // The agent service reports failures as `{"detail": ..., "run_id": ...}`
+        let error_json = serde_json::from_str::<serde_json::Value>(&error_text).ok();
+        let message = error_json
+            .as_ref()
+            .and_then(|v| v.get("detail"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .unwrap_or(error_text);
+        let run_id = error_json
+            .as_ref()
+            .and_then(|v| v.get("run_id"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        return Err(ApiError::Agent {
+            agent: agent_name.to_string(),
+            status: status.as_u16(),
+            message,
+            run_id,
+        }
+        .into());
     }
 
     let agent_response: AgentResponse = response
         .json()
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(ApiError::internal)?;
 
     trace!("Agent response: {:?}", agent_response);
     Ok(agent_response)
@@ -0,0 +1,216 @@
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+use crt_core::catalogue::MetricCatalogue;
+use crt_core::{negation::negate_crt, parser::parse_crt};
+use crt_metrics::{
+    events::{compute_event_metrics, load_records, DeploymentEvent, EventOptions, IncidentEvent, OutlierPolicy},
+    git::{commit_time, compute_git_metrics, DeploymentSource, GitOptions},
+    work_items::{allocate, read_work_items, AllocationRules},
+    MetricsReport, Timestamp, UNMEASURED_SLIDER,
+};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Parser)]
+#[command(name = "crt", about = "Current Reality Tree analysis tools")]
+struct Cli {
+    /// Metric catalogue file (TOML or JSON); defaults to the built-in metrics.
+    #[arg(long, global = true)]
+    catalogue: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compute metric values from delivery data.
+    Metrics {
+        #[command(subcommand)]
+        source: MetricsSource,
+    },
+    /// Derive a time allocation from a Jira or Azure Boards CSV export.
+    Allocation(AllocationArgs),
+    /// Derive a Future Reality Tree skeleton by negating a CRT from injected root causes.
+    Negate(NegateArgs),
+}
+
+#[derive(Subcommand)]
+enum MetricsSource {
+    /// Read deployments, commits and merges from a local git repository.
+    Git(GitArgs),
+    /// Read deployment and incident exports (CSV with a header row, or JSON arrays).
+    Events(EventArgs),
+}
+
+#[derive(Args)]
+struct OutputArgs {
+    /// Print only the slider positions, as the `dora_metrics`,
+    /// `extended_engineering_metrics` and `metrics` fields of an analyse request.
+    #[arg(long)]
+    sliders: bool,
+}
+
+#[derive(Args)]
+struct GitArgs {
+    /// Path to the repository.
+    #[arg(long, default_value = ".")]
+    repo: PathBuf,
+    /// Tags marking deployments, e.g. `deploy-*`.
+    #[arg(long, conflicts_with = "deploy_branch", required_unless_present = "deploy_branch")]
+    deploy_tags: Option<String>,
+    /// Branch whose first-parent commits are deployments, e.g. `production`.
+    #[arg(long)]
+    deploy_branch: Option<String>,
+    /// Branch feature branches merge into.
+    #[arg(long, default_value = "main")]
+    main_branch: String,
+    /// Length of the window, ending now.
+    #[arg(long, default_value_t = 30)]
+    days: u32,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args)]
+struct EventArgs {
+    /// Deployments with `timestamp` and optional `id`, `commit`, `committed_at`, `status`.
+    #[arg(long)]
+    deployments: PathBuf,
+    /// Incidents with `opened` and optional `resolved`, `caused_by`.
+    #[arg(long)]
+    incidents: Option<PathBuf>,
+    /// Repository used to look up `committed_at` for deployments naming only a commit.
+    #[arg(long)]
+    repo: Option<PathBuf>,
+    /// Length of the window.
+    #[arg(long, default_value_t = 30)]
+    days: u32,
+    /// End of the window (unix seconds or a date); defaults to now.
+    #[arg(long)]
+    until: Option<Timestamp>,
+    /// `keep`, `iqr`, `iqr:<factor>` or `cap:<days>`, applied to lead and restore times.
+    #[arg(long, default_value = "keep")]
+    outliers: OutlierPolicy,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args)]
+struct AllocationArgs {
+    /// Work-item export with issue type, labels and logged time columns.
+    #[arg(long)]
+    items: PathBuf,
+    /// TOML rule file mapping issue types and labels onto time buckets.
+    #[arg(long)]
+    rules: PathBuf,
+}
+
+#[derive(Args)]
+struct NegateArgs {
+    /// CRT document.
+    #[arg(long)]
+    crt: PathBuf,
+    /// Entity ids to inject against, e.g. `19,25`.
+    #[arg(long, required = true, value_delimiter = ',')]
+    inject: Vec<u32>,
+    /// Print the skeleton and UDE split as JSON instead of the FRT document.
+    #[arg(long)]
+    json: bool,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let catalogue = match &cli.catalogue {
+        Some(path) => MetricCatalogue::load(path)?,
+        None => MetricCatalogue::builtin(),
+    };
+
+    match cli.command {
+        Command::Metrics { source } => match source {
+            MetricsSource::Git(args) => {
+                let until = now()?;
+                let deployments = match (args.deploy_tags, args.deploy_branch) {
+                    (Some(pattern), _) => DeploymentSource::Tags(pattern),
+                    (None, Some(branch)) => DeploymentSource::Branch(branch),
+                    (None, None) => unreachable!("clap requires one deployment source"),
+                };
+                let options = GitOptions {
+                    repo: args.repo,
+                    deployments,
+                    main_branch: args.main_branch,
+                    since: until - i64::from(args.days) * 86_400,
+                    until,
+                };
+                print_report(&compute_git_metrics(&options, &catalogue)?, &args.output)
+            }
+            MetricsSource::Events(args) => {
+                let mut deployments: Vec<DeploymentEvent> = load_records(&args.deployments)?;
+                let incidents: Vec<IncidentEvent> = match &args.incidents {
+                    Some(path) => load_records(path)?,
+                    None => Vec::new(),
+                };
+                if let Some(repo) = &args.repo {
+                    for deployment in deployments.iter_mut().filter(|d| d.committed_at.is_none()) {
+                        if let Some(commit) = &deployment.commit {
+                            deployment.committed_at = Some(Timestamp(commit_time(repo, commit)?));
+                        }
+                    }
+                }
+                let until = match args.until {
+                    Some(until) => until.0,
+                    None => now()?,
+                };
+                let options = EventOptions {
+                    since: until - i64::from(args.days) * 86_400,
+                    until,
+                    outliers: args.outliers,
+                };
+                print_report(&compute_event_metrics(&deployments, &incidents, &options, &catalogue)?, &args.output)
+            }
+        },
+        Command::Allocation(args) => {
+            let rules = AllocationRules::load(&args.rules)?;
+            let export = std::fs::read_to_string(&args.items)?;
+            let report = allocate(&read_work_items(&export, &rules.columns)?, &rules)?;
+            if report.unmatched > 0 {
+                eprintln!("warning: {} item(s) matched no rule and count as {}", report.unmatched, rules.default_bucket);
+            }
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(())
+        }
+        Command::Negate(args) => {
+            let crt = parse_crt(&std::fs::read_to_string(&args.crt)?)?;
+            let skeleton = negate_crt(&crt, &args.inject)?;
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&skeleton)?);
+            } else {
+                let ids = |ids: &[u32]| ids.iter().map(|id| format!("E{id}")).collect::<Vec<_>>().join(", ");
+                eprintln!("neutralised UDEs: {}", ids(&skeleton.neutralised_udes));
+                eprintln!("remaining UDEs: {}", ids(&skeleton.remaining_udes));
+                print!("{}", skeleton.frt);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn now() -> Result<i64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64)
+}
+
+fn print_report(report: &MetricsReport, output: &OutputArgs) -> Result<()> {
+    for warning in &report.warnings {
+        eprintln!("warning: {warning}");
+    }
+    let json = if output.sliders {
+        for name in report.unmeasured() {
+            eprintln!("warning: {name} was not computed; its slider is left at {UNMEASURED_SLIDER}");
+        }
+        serde_json::to_string_pretty(&report.sliders())?
+    } else {
+        serde_json::to_string_pretty(report)?
+    };
+    println!("{json}");
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use crate::dora::{DoraMetricConfig, DORA_METRIC_CONFIGS};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A named metric: how its slider maps onto real values, plus the group it is
+/// shown under (`dora`, `engineering`, or anything an organisation adds).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MetricDefinition {
+    pub name: String,
+    #[serde(default = "default_group")]
+    pub group: String,
+    #[serde(flatten)]
+    pub config: DoraMetricConfig,
+}
+
+fn default_group() -> String {
+    "custom".to_string()
+}
+
+/// The metrics a deployment knows about. Starts from the built-in
+/// `DORA_METRIC_CONFIGS`; a catalogue file can override their configs and add
+/// further metrics, but not remove the built-in ones.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricCatalogue {
+    pub metrics: Vec<MetricDefinition>,
+}
+
+#[derive(Deserialize)]
+struct CatalogueFile {
+    metrics: Vec<MetricDefinition>,
+}
+
+impl Default for MetricCatalogue {
+    fn default() -> Self {
+        MetricCatalogue::builtin()
+    }
+}
+
+impl MetricCatalogue {
+    pub fn builtin() -> Self {
+        let metrics = DORA_METRIC_CONFIGS
+            .iter()
+            .map(|(name, config)| MetricDefinition {
+                name: name.to_string(),
+                group: builtin_group(name).to_string(),
+                config: config.clone(),
+            })
+            .collect();
+        MetricCatalogue { metrics }
+    }
+
+    /// Parse a catalogue from JSON: `{"metrics": [{"name": ..., "unit": ..., ...}]}`.
+    pub fn from_json(content: &str) -> Result<Self> {
+        let file: CatalogueFile = serde_json::from_str(content).context("Invalid metric catalogue JSON")?;
+        Self::builtin().extend(file.metrics)
+    }
+
+    /// Parse a catalogue from TOML, one `[[metrics]]` table per metric.
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let file: CatalogueFile = toml::from_str(content).context("Invalid metric catalogue TOML")?;
+        Self::builtin().extend(file.metrics)
+    }
+
+    /// Load a catalogue file, choosing the format from its extension
+    /// (`.toml`, otherwise JSON).
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read metric catalogue {}", path.display()))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&content),
+            _ => Self::from_json(&content),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&DoraMetricConfig> {
+        self.definition(name).map(|definition| &definition.config)
+    }
+
+    pub fn definition(&self, name: &str) -> Option<&MetricDefinition> {
+        self.metrics.iter().find(|definition| definition.name == name)
+    }
+
+    /// Add or replace definitions, checking each one.
+    fn extend(mut self, definitions: Vec<MetricDefinition>) -> Result<Self> {
+        let mut seen = std::collections::HashSet::new();
+        for definition in definitions {
+            if definition.name.trim().is_empty() {
+                bail!("Metric catalogue entries need a name");
+            }
+            if !seen.insert(definition.name.clone()) {
+                bail!("Metric '{}' is defined more than once", definition.name);
+            }
+            definition
+                .config
+                .check()
+                .map_err(|message| anyhow!("Metric '{}': {message}", definition.name))?;
+
+            match self.metrics.iter_mut().find(|existing| existing.name == definition.name) {
+                Some(existing) => *existing = definition,
+                None => self.metrics.push(definition),
+            }
+        }
+        Ok(self)
+    }
+}
+
+fn builtin_group(name: &str) -> &'static str {
+    match name {
+        "deployment_frequency" | "lead_time" | "change_failure_rate" | "mttr" => "dora",
+        _ => "engineering",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dora::Scale;
+
+    #[test]
+    fn builtin_configs_pass_checks() {
+        for definition in MetricCatalogue::builtin().metrics {
+            assert!(definition.config.check().is_ok(), "{}", definition.name);
+        }
+    }
+
+    #[test]
+    fn toml_adds_and_overrides_metrics() {
+        let catalogue = MetricCatalogue::from_toml(
+            r#"
+            [[metrics]]
+            name = "rework_rate"
+            group = "engineering"
+            unit = "%"
+            min_value = 0
+            max_value = 100
+            inverted = true
+
+            [[metrics]]
+            name = "lead_time"
+            group = "dora"
+            unit = "days"
+            min_value = 0.04
+            max_value = 90
+            inverted = true
+            scale = { piecewise = [[0.0, 0.04], [0.5, 7.0], [1.0, 90.0]] }
+            "#,
+        )
+        .unwrap();
+
+        let rework = catalogue.definition("rework_rate").unwrap();
+        assert_eq!(rework.config.scale, Scale::Linear);
+        assert_eq!(rework.config.translate(0.25).value, 75.0);
+
+        assert_eq!(catalogue.get("lead_time").unwrap().max_value, 90.0);
+        assert_eq!(catalogue.metrics.len(), DORA_METRIC_CONFIGS.len() + 1);
+    }
+
+    #[test]
+    fn json_catalogue_is_checked() {
+        let catalogue = MetricCatalogue::from_json(
+            r#"{"metrics": [{"name": "on_call_pages", "unit": "pages/week", "min_value": 0, "max_value": 50, "inverted": true}]}"#,
+        )
+        .unwrap();
+        assert_eq!(catalogue.definition("on_call_pages").unwrap().group, "custom");
+
+        let error = MetricCatalogue::from_json(
+            r#"{"metrics": [{"name": "flow_efficiency", "unit": "%", "min_value": 0, "max_value": 100, "scale": "log"}]}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("flow_efficiency"));
+    }
+}
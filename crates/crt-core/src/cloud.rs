@@ -0,0 +1,349 @@
+//! Evaporating Clouds: the conflict behind a core problem. Objective A needs
+//! both B and C; B requires prerequisite D, C requires D', and D conflicts
+//! with D'. Each arrow rests on assumptions, and invalidating one of them is
+//! what evaporates the conflict.
+
+use crate::graph::CausalGraph;
+use crate::parser::{
+    document_text, grammar_diagnostic, line_terminated, CRTParser, EntityType, ParseDiagnostic, Rule, CRT,
+};
+use anyhow::{anyhow, Result};
+use pest::iterators::Pair;
+use pest::Parser;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub enum CloudBox {
+    A,
+    B,
+    C,
+    D,
+    #[serde(rename = "D'")]
+    DPrime,
+}
+
+impl CloudBox {
+    pub const ALL: [CloudBox; 5] = [CloudBox::A, CloudBox::B, CloudBox::C, CloudBox::D, CloudBox::DPrime];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CloudBox::A => "A",
+            CloudBox::B => "B",
+            CloudBox::C => "C",
+            CloudBox::D => "D",
+            CloudBox::DPrime => "D'",
+        }
+    }
+
+    /// What the box stands for in the diagram.
+    pub fn role(&self) -> &'static str {
+        match self {
+            CloudBox::A => "objective",
+            CloudBox::B | CloudBox::C => "need",
+            CloudBox::D | CloudBox::DPrime => "prerequisite",
+        }
+    }
+}
+
+impl fmt::Display for CloudBox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl FromStr for CloudBox {
+    type Err = String;
+
+    /// Accepts a typographic apostrophe in `D’`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let label = s.trim().replace('’', "'");
+        CloudBox::ALL.into_iter().find(|b| b.label() == label).ok_or_else(|| {
+            let labels: Vec<&str> = CloudBox::ALL.iter().map(CloudBox::label).collect();
+            format!("Unknown cloud box '{s}' (expected one of {})", labels.join(", "))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub enum CloudArrow {
+    #[serde(rename = "A-B")]
+    AB,
+    #[serde(rename = "A-C")]
+    AC,
+    #[serde(rename = "B-D")]
+    BD,
+    #[serde(rename = "C-D'")]
+    CDPrime,
+    /// The conflict between the two prerequisites.
+    #[serde(rename = "D-D'")]
+    Conflict,
+}
+
+impl CloudArrow {
+    pub const ALL: [CloudArrow; 5] = [
+        CloudArrow::AB,
+        CloudArrow::AC,
+        CloudArrow::BD,
+        CloudArrow::CDPrime,
+        CloudArrow::Conflict,
+    ];
+
+    /// The boxes the arrow joins, the one nearer the objective first.
+    pub fn ends(&self) -> (CloudBox, CloudBox) {
+        match self {
+            CloudArrow::AB => (CloudBox::A, CloudBox::B),
+            CloudArrow::AC => (CloudBox::A, CloudBox::C),
+            CloudArrow::BD => (CloudBox::B, CloudBox::D),
+            CloudArrow::CDPrime => (CloudBox::C, CloudBox::DPrime),
+            CloudArrow::Conflict => (CloudBox::D, CloudBox::DPrime),
+        }
+    }
+
+    /// The arrow joining `a` and `b`, in either order.
+    pub fn between(a: CloudBox, b: CloudBox) -> Option<Self> {
+        CloudArrow::ALL.into_iter().find(|arrow| arrow.ends() == (a, b) || arrow.ends() == (b, a))
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CloudArrow::AB => "A-B",
+            CloudArrow::AC => "A-C",
+            CloudArrow::BD => "B-D",
+            CloudArrow::CDPrime => "C-D'",
+            CloudArrow::Conflict => "D-D'",
+        }
+    }
+}
+
+impl fmt::Display for CloudArrow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct EvaporatingCloud {
+    /// Text of every box stated so far.
+    pub boxes: BTreeMap<CloudBox, String>,
+    /// Assumptions behind each arrow, in the order they were written.
+    #[serde(default)]
+    pub assumptions: BTreeMap<CloudArrow, Vec<String>>,
+}
+
+impl EvaporatingCloud {
+    pub fn text(&self, cloud_box: CloudBox) -> Option<&str> {
+        self.boxes.get(&cloud_box).map(String::as_str)
+    }
+
+    /// Boxes not stated yet.
+    pub fn missing(&self) -> Vec<CloudBox> {
+        CloudBox::ALL.into_iter().filter(|b| !self.boxes.contains_key(b)).collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.missing().is_empty()
+    }
+
+    /// Every arrow whose boxes are both stated, read as a sentence, e.g.
+    /// "In order to deliver predictably, we must protect planned work".
+    pub fn readings(&self) -> Vec<String> {
+        CloudArrow::ALL
+            .into_iter()
+            .filter_map(|arrow| {
+                let (near, far) = arrow.ends();
+                let (near, far) = (self.text(near)?, self.text(far)?);
+                Some(match arrow {
+                    CloudArrow::Conflict => format!("\"{near}\" conflicts with \"{far}\""),
+                    _ => format!("In order to {near}, we must {far}"),
+                })
+            })
+            .collect()
+    }
+
+    /// A draft for the core conflict behind `root_cause` of `crt`: the root
+    /// cause becomes D, the prerequisite the team acts on today, and the other
+    /// boxes are left for the author or the agent. `None` when the entity is
+    /// not in the tree.
+    pub fn from_root_cause(crt: &CRT, root_cause: u32) -> Option<Self> {
+        let entity = crt.entities.get(&root_cause)?;
+        let mut cloud = EvaporatingCloud::default();
+        cloud.boxes.insert(CloudBox::D, entity.text.clone());
+        Some(cloud)
+    }
+}
+
+impl fmt::Display for EvaporatingCloud {
+    /// The cloud in the grammar `parse_cloud` reads.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Cloud")?;
+        for (cloud_box, text) in &self.boxes {
+            writeln!(f, "{cloud_box}. {}", document_text(text))?;
+        }
+        if self.assumptions.values().any(|assumptions| !assumptions.is_empty()) {
+            writeln!(f, "\nAssumptions")?;
+            for (arrow, assumptions) in &self.assumptions {
+                for assumption in assumptions {
+                    writeln!(f, "{arrow}. {}", document_text(assumption))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The root cause a core conflict is most worth building around: among the
+/// entities typed `root_cause` (or the tree's sources when none is typed), the
+/// one leading to the most final effects. Ties go to the lowest id.
+pub fn core_root_cause(crt: &CRT) -> Option<u32> {
+    let graph = CausalGraph::from_crt(crt);
+    let typed: Vec<u32> = crt
+        .entities
+        .values()
+        .filter(|entity| entity.attributes.kind == Some(EntityType::RootCause))
+        .map(|entity| entity.id)
+        .collect();
+    let candidates = if typed.is_empty() { graph.sources() } else { typed };
+    candidates
+        .into_iter()
+        .max_by_key(|&id| (graph.effects_of(id).len(), std::cmp::Reverse(id)))
+}
+
+// ---------- API ----------
+pub fn parse_cloud(input: &str) -> Result<EvaporatingCloud> {
+    let source = line_terminated(input);
+    let mut pairs = CRTParser::parse(Rule::cloud_file, source.as_ref()).map_err(grammar_diagnostic)?;
+    let file = pairs.next().unwrap();
+
+    let mut cloud = EvaporatingCloud::default();
+    for line in file.into_inner() {
+        match line.as_rule() {
+            Rule::cloud_line => {
+                let (cloud_box, text) = parse_cloud_line(line.clone()).map_err(|e| ParseDiagnostic::at(&line, e))?;
+                if cloud.boxes.insert(cloud_box, text).is_some() {
+                    return Err(ParseDiagnostic::at(&line, format!("Duplicate box {cloud_box}")).into());
+                }
+            }
+            Rule::assumption_line => {
+                let (arrow, text) = parse_assumption_line(line.clone()).map_err(|e| ParseDiagnostic::at(&line, e))?;
+                cloud.assumptions.entry(arrow).or_default().push(text);
+            }
+            // headings/blanklines are already consumed in the grammar
+            _ => {}
+        }
+    }
+    Ok(cloud)
+}
+
+// ---------- parsers ----------
+fn parse_cloud_line(p: Pair<Rule>) -> Result<(CloudBox, String)> {
+    // cloud_line = { ws* cloud_box "." ws* cloud_text eol }
+    let mut cloud_box = None;
+    let mut text = String::new();
+    for part in p.into_inner() {
+        match part.as_rule() {
+            Rule::cloud_box => cloud_box = Some(part.as_str().parse::<CloudBox>().map_err(|e| anyhow!(e))?),
+            Rule::cloud_text => text = part.as_str().trim().to_string(),
+            _ => {}
+        }
+    }
+    let cloud_box = cloud_box.ok_or_else(|| anyhow!("Missing cloud box"))?;
+    Ok((cloud_box, text))
+}
+
+fn parse_assumption_line(p: Pair<Rule>) -> Result<(CloudArrow, String)> {
+    // assumption_line = { ws* cloud_arrow "." ws* cloud_text eol }
+    let mut arrow = None;
+    let mut text = String::new();
+    for part in p.into_inner() {
+        match part.as_rule() {
+            Rule::cloud_arrow => {
+                let boxes = part
+                    .into_inner()
+                    .map(|b| b.as_str().parse::<CloudBox>().map_err(|e| anyhow!(e)))
+                    .collect::<Result<Vec<_>>>()?;
+                let (a, b) = (boxes[0], boxes[1]);
+                arrow = Some(CloudArrow::between(a, b).ok_or_else(|| {
+                    let labels: Vec<&str> = CloudArrow::ALL.iter().map(CloudArrow::label).collect();
+                    anyhow!("No arrow joins {a} and {b} (expected one of {})", labels.join(", "))
+                })?);
+            }
+            Rule::cloud_text => text = part.as_str().trim().to_string(),
+            _ => {}
+        }
+    }
+    let arrow = arrow.ok_or_else(|| anyhow!("Missing arrow"))?;
+    Ok((arrow, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_crt;
+
+    const CLOUD: &str = "Cloud\n\
+        A. Deliver predictably\n\
+        B. Respond to urgent customer requests\n\
+        C. Protect planned work\n\
+        D. Accept interruptions mid-sprint // today\n\
+        D’. Refuse interruptions mid-sprint\n\
+        \n\
+        Assumptions\n\
+        B-D. Urgent requests cannot wait for the next sprint\n\
+        D'-C. Planned work only finishes when nobody interrupts it\n\
+        B - D. Customers leave when we are slow\n\
+        D-D'. A sprint cannot be both open and closed\n";
+
+    #[test]
+    fn parses_and_exports_cloud() {
+        let cloud = parse_cloud(CLOUD).unwrap();
+        assert!(cloud.is_complete());
+        assert_eq!(cloud.text(CloudBox::D), Some("Accept interruptions mid-sprint"));
+        assert_eq!(cloud.assumptions[&CloudArrow::BD].len(), 2);
+        assert_eq!(cloud.assumptions[&CloudArrow::CDPrime].len(), 1);
+        assert_eq!(cloud.readings()[0], "In order to Deliver predictably, we must Respond to urgent customer requests");
+
+        let exported = cloud.to_string();
+        assert!(exported.contains("C-D'. Planned work only finishes"));
+        assert_eq!(parse_cloud(&exported).unwrap(), cloud);
+    }
+
+    #[test]
+    fn rejects_invalid_clouds() {
+        let error = parse_cloud("Cloud\nA. One\nA. Two\n").unwrap_err();
+        let diagnostic = error.downcast_ref::<ParseDiagnostic>().unwrap();
+        assert_eq!((diagnostic.line, diagnostic.message.as_str()), (3, "Duplicate box A"));
+
+        let error = parse_cloud("Cloud\nA. One\n\nAssumptions\nA-D. Not an arrow\n").unwrap_err();
+        assert!(error.to_string().contains("No arrow joins A and D"));
+
+        let partial = parse_cloud("Cloud\nD. Release in large batches\n").unwrap();
+        assert_eq!(partial.missing(), vec![CloudBox::A, CloudBox::B, CloudBox::C, CloudBox::DPrime]);
+    }
+
+    #[test]
+    fn drafts_cloud_from_core_root_cause() {
+        let crt = parse_crt(
+            "Entities\n\
+             E1. Small side issue\n\
+             E2. Work is released in large batches\n\
+             E3. Releases are risky\n\
+             E4. Customers wait for fixes\n\
+             E5. Outages last long\n\
+             \n\
+             Links\n\
+             L1. E1 → E4\n\
+             L2. E2 → E3\n\
+             L3. E3 → E4\n\
+             L4. E3 → E5\n",
+        )
+        .unwrap();
+        assert_eq!(core_root_cause(&crt), Some(2));
+
+        let draft = EvaporatingCloud::from_root_cause(&crt, 2).unwrap();
+        assert_eq!(draft.text(CloudBox::D), Some("Work is released in large batches"));
+        assert!(EvaporatingCloud::from_root_cause(&crt, 9).is_none());
+    }
+}
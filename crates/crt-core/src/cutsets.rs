@@ -0,0 +1,219 @@
+//! Minimal cut sets: the smallest sets of entities whose removal leaves a
+//! UDE without any chain from the root causes, as candidate injections for
+//! the planner.
+
+use crate::negation::{junctions, neutralised};
+use crate::parser::{Entity, CRT};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Tags like `cost:low` or `cost:4` estimate what removing an entity takes.
+pub const COST_TAG_PREFIX: &str = "cost:";
+/// The cost of removing an entity without a cost tag, that of `cost:medium`.
+pub const DEFAULT_REMOVAL_COST: f32 = 2.0;
+
+/// The estimated cost of removing `entity`, from its first cost tag:
+/// `low`, `medium` and `high` are 1, 2 and 3, and a positive number stands
+/// for itself. An invalid first cost tag gives none, whatever follows it.
+pub fn removal_cost(entity: &Entity) -> Option<f32> {
+    let value = entity.attributes.tags.iter().find_map(|tag| tag.strip_prefix(COST_TAG_PREFIX))?.trim();
+    match value.to_ascii_lowercase().as_str() {
+        "low" => Some(1.0),
+        "medium" => Some(2.0),
+        "high" => Some(3.0),
+        _ => value.parse().ok().filter(|cost: &f32| cost.is_finite() && *cost > 0.0),
+    }
+}
+
+/// Bounds on the search for cut sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CutSetLimits {
+    /// Larger sets are not tried.
+    pub max_size: usize,
+    /// Candidate sets tested before the search stops.
+    pub max_checks: usize,
+    /// Cut sets kept after ranking.
+    pub max_sets: usize,
+}
+
+impl Default for CutSetLimits {
+    fn default() -> Self {
+        CutSetLimits {
+            max_size: 3,
+            max_checks: 20_000,
+            max_sets: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CutSet {
+    pub entities: Vec<u32>,
+    /// Sum of the entities' removal costs.
+    pub cost: f32,
+    /// Entities without a cost tag, priced at `DEFAULT_REMOVAL_COST`.
+    pub untagged: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct CutSets {
+    pub target: u32,
+    /// Cheapest first, then smallest.
+    pub cut_sets: Vec<CutSet>,
+    /// Whether `max_checks` cut the search short.
+    pub truncated: bool,
+}
+
+/// The minimal sets of entities upstream of `target` whose removal stops it
+/// holding, as `negate_crt` judges it: a junction of `AND`-ed causes is lost
+/// with any one of them, and a loop is lost once nothing outside sustains
+/// it. Sets are tried smallest first, skipping any that contain a cut
+/// already found, so every set returned is minimal. A target nothing leads
+/// to has none.
+pub fn minimal_cut_sets(crt: &CRT, target: u32, limits: CutSetLimits) -> Result<CutSets> {
+    if !crt.entities.contains_key(&target) {
+        return Err(anyhow!("Entity E{target} is not in the CRT"));
+    }
+    let causes = junctions(crt);
+
+    let mut upstream = BTreeSet::new();
+    let mut stack = vec![target];
+    while let Some(id) = stack.pop() {
+        for &cause in causes.get(&id).into_iter().flatten().flatten() {
+            if cause != target && upstream.insert(cause) {
+                stack.push(cause);
+            }
+        }
+    }
+    let candidates: Vec<u32> = upstream.into_iter().collect();
+
+    let mut found: Vec<BTreeSet<u32>> = Vec::new();
+    let mut checks = 0;
+    let mut truncated = false;
+    'sizes: for size in 1..=limits.max_size.min(candidates.len()) {
+        let mut picked: Vec<usize> = (0..size).collect();
+        loop {
+            let set: BTreeSet<u32> = picked.iter().map(|&index| candidates[index]).collect();
+            if !found.iter().any(|cut| cut.is_subset(&set)) {
+                if checks == limits.max_checks {
+                    truncated = true;
+                    break 'sizes;
+                }
+                checks += 1;
+                if neutralised(crt, &causes, &set).contains(&target) {
+                    found.push(set);
+                }
+            }
+            if !next_combination(&mut picked, candidates.len()) {
+                break;
+            }
+        }
+    }
+
+    let mut cut_sets: Vec<CutSet> = found
+        .into_iter()
+        .map(|set| {
+            let costs: Vec<(u32, Option<f32>)> = set.iter().map(|id| (*id, removal_cost(&crt.entities[id]))).collect();
+            CutSet {
+                cost: costs.iter().map(|(_, cost)| cost.unwrap_or(DEFAULT_REMOVAL_COST)).sum(),
+                untagged: costs.iter().filter(|(_, cost)| cost.is_none()).map(|(id, _)| *id).collect(),
+                entities: set.into_iter().collect(),
+            }
+        })
+        .collect();
+    cut_sets.sort_by(|a, b| {
+        a.cost
+            .total_cmp(&b.cost)
+            .then(a.entities.len().cmp(&b.entities.len()))
+            .then(a.entities.cmp(&b.entities))
+    });
+    cut_sets.truncate(limits.max_sets);
+    Ok(CutSets {
+        target,
+        cut_sets,
+        truncated,
+    })
+}
+
+/// Advance `picked`, increasing indices below `n`, to the next combination
+/// in lexicographic order; false after the last.
+fn next_combination(picked: &mut [usize], n: usize) -> bool {
+    let size = picked.len();
+    let Some(position) = (0..size).rev().find(|&position| picked[position] < n - size + position) else {
+        return false;
+    };
+    picked[position] += 1;
+    for next in position + 1..size {
+        picked[next] = picked[next - 1] + 1;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_crt;
+
+    const TREE: &str = "Entities\n\
+        E1. Changes need manual approval {type: root_cause, tags: [cost:high]}\n\
+        E2. Tests run only before releases {type: root_cause, tags: [flow, cost:low]}\n\
+        E3. Releases batch up\n\
+        E4. Failures are hard to trace {type: ude}\n\
+        E5. Audits demand sign-off {tags: [cost:5]}\n\
+        E6. Support escalates\n\
+        \n\
+        Links\n\
+        L1. E1 AND E2 → E3 → E4\n\
+        L2. E5 → E6 → E4\n\
+        L3. E6 → E5\n";
+
+    #[test]
+    fn finds_minimal_cuts_through_and_junctions() {
+        let crt = parse_crt(TREE).unwrap();
+        let result = minimal_cut_sets(&crt, 4, CutSetLimits::default()).unwrap();
+
+        // one input of the AND junction is enough, and E5 and E6 only
+        // sustain each other once the other is gone
+        let ranked: Vec<(Vec<u32>, f32)> = result.cut_sets.iter().map(|cut| (cut.entities.clone(), cut.cost)).collect();
+        assert_eq!(
+            ranked,
+            vec![
+                (vec![2, 6], 3.0),
+                (vec![3, 6], 4.0),
+                (vec![1, 6], 5.0),
+                (vec![2, 5], 6.0),
+                (vec![3, 5], 7.0),
+                (vec![1, 5], 8.0),
+            ]
+        );
+        assert_eq!(result.cut_sets[0].untagged, vec![6]);
+        assert!(!result.truncated);
+
+        assert_eq!(minimal_cut_sets(&crt, 3, CutSetLimits::default()).unwrap().cut_sets.len(), 2);
+        assert!(minimal_cut_sets(&crt, 1, CutSetLimits::default()).unwrap().cut_sets.is_empty());
+        let error = minimal_cut_sets(&crt, 9, CutSetLimits::default()).unwrap_err();
+        assert!(error.to_string().contains("Entity E9 is not in the CRT"));
+    }
+
+    #[test]
+    fn bounds_the_search() {
+        let crt = parse_crt(TREE).unwrap();
+        let limits = |max_size, max_checks, max_sets| CutSetLimits { max_size, max_checks, max_sets };
+
+        assert!(minimal_cut_sets(&crt, 4, limits(1, 100, 10)).unwrap().cut_sets.is_empty());
+        assert_eq!(minimal_cut_sets(&crt, 4, limits(3, 100, 2)).unwrap().cut_sets.len(), 2);
+
+        // five single entities, then {E1, E2}, {E1, E3} and {E1, E5}
+        let result = minimal_cut_sets(&crt, 4, limits(3, 8, 10)).unwrap();
+        assert!(result.truncated);
+        assert_eq!(result.cut_sets.iter().map(|cut| cut.entities.clone()).collect::<Vec<_>>(), vec![vec![1, 5]]);
+    }
+
+    #[test]
+    fn reads_cost_tags() {
+        let crt = parse_crt("Entities\nE1. A {tags: [cost:Medium]}\nE2. B {tags: [cost:0.5]}\nE3. C {tags: [cost:-1, cost:soon]}\nE4. D {tags: [flow, cost:soon, cost:low]}\n\nLinks\nL1. E1 → E2 → E3 → E4\n").unwrap();
+        let costs: Vec<Option<f32>> = crt.entities.values().map(removal_cost).collect();
+        assert_eq!(costs, vec![Some(2.0), Some(0.5), None, None]);
+    }
+}
@@ -1,109 +1,447 @@
-use crate::types::{DoraMetric, DoraMetrics, EngineeringMetrics};
-use std::collections::HashMap;
+use crate::catalogue::MetricCatalogue;
+use crate::types::{DoraMetric, DoraMetricsInput, EngineeringMetrics, MetricInput};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Debug, Clone)]
+/// How a 0–1 slider position maps onto `min_value..=max_value`.
+///
+/// Positions are measured from `min_value`; for inverted metrics the slider is
+/// flipped first, so 1.0 always means "best".
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scale {
+    #[default]
+    Linear,
+    /// Equal slider steps multiply the value by equal factors; `min_value` must be positive.
+    Log,
+    /// Straight segments between `(position, value)` breakpoints, ascending in
+    /// both, starting at `(0.0, min_value)` and ending at `(1.0, max_value)`.
+    Piecewise(Cow<'static, [(f32, f32)]>),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct DoraMetricConfig {
     pub min_value: f32,
     pub max_value: f32,
-    pub unit: &'static str,
+    pub unit: Cow<'static, str>,
+    #[serde(default)]
     pub inverted: bool,
+    #[serde(default)]
+    pub scale: Scale,
 }
 
 impl DoraMetricConfig {
     pub fn translate(&self, slider_value: f32) -> DoraMetric {
-        let translated_value = if self.inverted {
-            self.max_value - (self.max_value - self.min_value) * slider_value
-        } else {
-            self.min_value + (self.max_value - self.min_value) * slider_value
+        let position = if self.inverted { 1.0 - slider_value } else { slider_value };
+        let translated_value = match &self.scale {
+            Scale::Linear => self.min_value + (self.max_value - self.min_value) * position,
+            Scale::Log => self.min_value * (self.max_value / self.min_value).powf(position),
+            Scale::Piecewise(breakpoints) => interpolate(breakpoints, position, |(p, v)| (p, v)),
+        };
+
+        DoraMetric {
+            value: self.format_value(translated_value),
+            unit: self.unit.to_string(),
+        }
+    }
+
+    /// Inverse of [`translate`](Self::translate): the slider position for a
+    /// value in this config's unit, clamped to 0–1.
+    pub fn to_slider(&self, value: f32) -> f32 {
+        if self.max_value <= self.min_value {
+            return 0.0;
+        }
+        let value = value.clamp(self.min_value, self.max_value);
+        let position = match &self.scale {
+            Scale::Linear => (value - self.min_value) / (self.max_value - self.min_value),
+            Scale::Log => (value / self.min_value).ln() / (self.max_value / self.min_value).ln(),
+            Scale::Piecewise(breakpoints) => interpolate(breakpoints, value, |(p, v)| (v, p)),
         };
+        let position = position.clamp(0.0, 1.0);
+        if self.inverted { 1.0 - position } else { position }
+    }
 
-        // Format value based on the unit
-        let formatted_value = if self.unit == "%" {
+    /// Convert a real value with its unit into this config's unit, checking it
+    /// lies within `min_value..=max_value`.
+    pub fn from_value(&self, metric: &DoraMetric) -> Result<DoraMetric, String> {
+        let value = convert_unit(metric.value, &metric.unit, &self.unit)
+            .ok_or_else(|| format!("Cannot convert '{}' to '{}'", metric.unit, self.unit))?;
+        if !value.is_finite() || value < self.min_value || value > self.max_value {
+            return Err(format!(
+                "Must be between {} and {} {}",
+                self.min_value, self.max_value, self.unit
+            ));
+        }
+        Ok(DoraMetric {
+            value: self.format_value(value),
+            unit: self.unit.to_string(),
+        })
+    }
+
+    /// Check the range and scale are usable, for configs loaded from a file.
+    pub fn check(&self) -> Result<(), String> {
+        if !self.min_value.is_finite() || !self.max_value.is_finite() || self.min_value >= self.max_value {
+            return Err(format!("min_value ({}) must be below max_value ({})", self.min_value, self.max_value));
+        }
+        match &self.scale {
+            Scale::Linear => {}
+            Scale::Log => {
+                if self.min_value <= 0.0 {
+                    return Err("log scale needs a positive min_value".to_string());
+                }
+            }
+            Scale::Piecewise(breakpoints) => {
+                let (first, last) = match (breakpoints.first(), breakpoints.last()) {
+                    (Some(first), Some(last)) if breakpoints.len() >= 2 => (*first, *last),
+                    _ => return Err("piecewise scale needs at least two breakpoints".to_string()),
+                };
+                if first != (0.0, self.min_value) || last != (1.0, self.max_value) {
+                    return Err("piecewise scale must run from (0, min_value) to (1, max_value)".to_string());
+                }
+                if breakpoints.windows(2).any(|pair| pair[1].0 < pair[0].0 || pair[1].1 < pair[0].1) {
+                    return Err("piecewise breakpoints must ascend".to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn format_value(&self, value: f32) -> f32 {
+        if self.unit == "%" {
             // For percentages, round to nearest integer but keep as f32
-            translated_value.round()
+            value.round()
         } else {
             // For days, show 2 decimal places
-            (translated_value * 1000.0).round() / 1000.0
-        };
+            (value * 1000.0).round() / 1000.0
+        }
+    }
+}
 
-        DoraMetric {
-            value: formatted_value,
-            unit: self.unit.to_string(),
+/// Linear interpolation through `breakpoints`, with `axes` choosing which
+/// coordinate of each breakpoint is the input and which the output.
+fn interpolate(breakpoints: &[(f32, f32)], x: f32, axes: impl Fn((f32, f32)) -> (f32, f32)) -> f32 {
+    let points: Vec<(f32, f32)> = breakpoints.iter().copied().map(axes).collect();
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return x,
+    };
+    if x <= first.0 {
+        return first.1;
+    }
+    for pair in points.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        if x <= x1 {
+            if x1 == x0 {
+                return y1;
+            }
+            return y0 + (y1 - y0) * (x - x0) / (x1 - x0);
         }
     }
+    last.1
+}
+
+/// Length of a time unit in days, accepting singular, plural and short forms.
+fn duration_in_days(unit: &str) -> Option<f32> {
+    match unit.trim() {
+        "minute" | "minutes" | "min" => Some(1.0 / 1440.0),
+        "hour" | "hours" | "h" => Some(1.0 / 24.0),
+        "day" | "days" | "d" => Some(1.0),
+        "week" | "weeks" | "w" => Some(7.0),
+        _ => None,
+    }
+}
+
+/// Convert `value` from one unit to another. Supports durations (`hours`,
+/// `days`, `weeks`, ...) and rates over them with the same numerator, such as
+/// `deployments/week` to `deployments/day`. Returns `None` for incompatible units.
+pub fn convert_unit(value: f32, from: &str, to: &str) -> Option<f32> {
+    if from.trim() == to.trim() {
+        return Some(value);
+    }
+    if let (Some(from_days), Some(to_days)) = (duration_in_days(from), duration_in_days(to)) {
+        return Some(value * from_days / to_days);
+    }
+
+    // rates: "<count>/<period>[ <qualifier>]", e.g. "commits/day per developer"
+    let split_rate = |unit: &str| -> Option<(String, f32, String)> {
+        let (count, rest) = unit.split_once('/')?;
+        let (period, qualifier) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+        Some((count.trim().to_string(), duration_in_days(period)?, qualifier.trim().to_string()))
+    };
+    let (from_count, from_days, from_qualifier) = split_rate(from)?;
+    let (to_count, to_days, to_qualifier) = split_rate(to)?;
+    if from_count != to_count || from_qualifier != to_qualifier {
+        return None;
+    }
+    Some(value * to_days / from_days)
 }
 
 pub const DORA_METRIC_CONFIGS: &[(&str, DoraMetricConfig)] = &[
+    // one decade per quarter of the slider: every ~3 years .. 10 per day
     ("deployment_frequency", DoraMetricConfig {
         min_value: 0.001,
         max_value: 10.0,
-        unit: "deployments/day",
+        unit: Cow::Borrowed("deployments/day"),
         inverted: false,
+        scale: Scale::Log,
     }),
+    // breakpoints at the DORA tier boundaries: 1 day, 1 week, 1 month
     ("lead_time", DoraMetricConfig {
         min_value: 0.04,
         max_value: 60.0,
-        unit: "days",
+        unit: Cow::Borrowed("days"),
         inverted: true,
+        scale: Scale::Piecewise(Cow::Borrowed(&[(0.0, 0.04), (0.25, 1.0), (0.5, 7.0), (0.75, 30.0), (1.0, 60.0)])),
     }),
     ("change_failure_rate", DoraMetricConfig {
         min_value: 0.0,
         max_value: 100.0,
-        unit: "%",
+        unit: Cow::Borrowed("%"),
         inverted: true,
+        scale: Scale::Linear,
     }),
+    // breakpoints at the DORA tier boundaries: 1 hour, 1 day, 1 week
     ("mttr", DoraMetricConfig {
         min_value: 0.0125,
         max_value: 14.0,
-        unit: "days",
+        unit: Cow::Borrowed("days"),
         inverted: true,
+        scale: Scale::Piecewise(Cow::Borrowed(&[(0.0, 0.0125), (0.25, 0.0417), (0.5, 1.0), (0.75, 7.0), (1.0, 14.0)])),
     }),
     ("commit_frequency", DoraMetricConfig {
         min_value: 0.0625,
         max_value: 10.0,
-        unit: "commits/day per developer",
+        unit: Cow::Borrowed("commits/day per developer"),
         inverted: false,
+        scale: Scale::Linear,
     }),
     ("branch_lifetime", DoraMetricConfig {
         min_value: 0.0125,
         max_value: 30.0,
-        unit: "days",
+        unit: Cow::Borrowed("days"),
         inverted: true,
+        scale: Scale::Linear,
+    }),
+    ("pbis_delivered_per_sprint_per_team", DoraMetricConfig {
+        min_value: 0.0,
+        max_value: 20.0,
+        unit: Cow::Borrowed("PBIs/sprint/team"),
+        inverted: false,
+        scale: Scale::Linear,
     }),
 ];
 
-pub fn translate_dora_metrics_for_agent(dora_metrics: &DoraMetrics) -> HashMap<String, DoraMetric> {
-    let get_config = |metric_name: &str| -> &DoraMetricConfig {
-        DORA_METRIC_CONFIGS
-            .iter()
-            .find(|(name, _)| *name == metric_name)
-            .map(|(_, config)| config)
-            .expect("Unknown DORA metric")
-    };
+/// Slider position translated through the catalogue entry for `name`. Metrics
+/// missing from the catalogue are passed on as the raw slider position.
+fn translate_slider(catalogue: &MetricCatalogue, name: &str, slider_value: f32) -> DoraMetric {
+    match catalogue.get(name) {
+        Some(config) => config.translate(slider_value),
+        None => DoraMetric {
+            value: slider_value,
+            unit: "slider position".to_string(),
+        },
+    }
+}
+
+/// Real value converted to the catalogue unit for `name`. Requests are
+/// validated first, so conversion only fails for unvalidated input, in which
+/// case the value is passed on unchanged.
+fn translate_value(catalogue: &MetricCatalogue, name: &str, metric: &DoraMetric) -> DoraMetric {
+    catalogue
+        .get(name)
+        .and_then(|config| config.from_value(metric).ok())
+        .unwrap_or_else(|| metric.clone())
+}
 
+pub fn translate_dora_metrics_for_agent(catalogue: &MetricCatalogue, dora_metrics: &DoraMetricsInput) -> HashMap<String, DoraMetric> {
     let mut result = HashMap::new();
-    result.insert("deployment_frequency".to_string(), get_config("deployment_frequency").translate(dora_metrics.deployment_frequency));
-    result.insert("lead_time".to_string(), get_config("lead_time").translate(dora_metrics.lead_time));
-    result.insert("change_failure_rate".to_string(), get_config("change_failure_rate").translate(dora_metrics.change_failure_rate));
-    result.insert("mttr".to_string(), get_config("mttr").translate(dora_metrics.mttr));
+    match dora_metrics {
+        DoraMetricsInput::Sliders(sliders) => {
+            for (name, slider_value) in sliders.iter() {
+                result.insert(name.to_string(), translate_slider(catalogue, name, slider_value));
+            }
+        }
+        DoraMetricsInput::Values(values) => {
+            for (name, metric) in values.iter() {
+                result.insert(name.to_string(), translate_value(catalogue, name, metric));
+            }
+        }
+    }
     result
 }
 
-pub fn translate_engineering_metrics_for_agent(engineering_metrics: &EngineeringMetrics) -> HashMap<String, DoraMetric> {
-    let get_config = |metric_name: &str| -> &DoraMetricConfig {
-        DORA_METRIC_CONFIGS
-            .iter()
-            .find(|(name, _)| *name == metric_name)
-            .map(|(_, config)| config)
-            .expect("Unknown DORA metric")
-    };
-
+pub fn translate_engineering_metrics_for_agent(catalogue: &MetricCatalogue, engineering_metrics: &EngineeringMetrics) -> HashMap<String, DoraMetric> {
     let mut result = HashMap::new();
-    result.insert("commit_frequency".to_string(), get_config("commit_frequency").translate(engineering_metrics.commit_frequency));
-    result.insert("branch_lifetime".to_string(), get_config("branch_lifetime").translate(engineering_metrics.branch_lifetime));
-    result.insert("pbis_delivered_per_sprint_per_team".to_string(), DoraMetric {
-        value: engineering_metrics.pbis_delivered_per_sprint_per_team,
-        unit: "PBIs/sprint/team".to_string(),
-    });
+    result.insert("commit_frequency".to_string(), translate_slider(catalogue, "commit_frequency", engineering_metrics.commit_frequency));
+    result.insert("branch_lifetime".to_string(), translate_slider(catalogue, "branch_lifetime", engineering_metrics.branch_lifetime));
+    result.insert("pbis_delivered_per_sprint_per_team".to_string(), translate_slider(catalogue, "pbis_delivered_per_sprint_per_team", engineering_metrics.pbis_delivered_per_sprint_per_team));
     result
 }
+
+/// Translate the open `metrics` map of a request: slider positions and real
+/// values alike end up in each metric's catalogue unit.
+pub fn translate_metrics_for_agent(catalogue: &MetricCatalogue, metrics: &BTreeMap<String, MetricInput>) -> HashMap<String, DoraMetric> {
+    metrics
+        .iter()
+        .map(|(name, input)| {
+            let metric = match input {
+                MetricInput::Slider(slider_value) => translate_slider(catalogue, name, *slider_value),
+                MetricInput::Value(metric) => translate_value(catalogue, name, metric),
+            };
+            (name.clone(), metric)
+        })
+        .collect()
+}
+
+/// DORA performance tier. Ordered from best to worst, so `max` picks the worse tier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub enum DoraTier {
+    Elite,
+    High,
+    Medium,
+    Low,
+}
+
+impl DoraTier {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DoraTier::Elite => "Elite",
+            DoraTier::High => "High",
+            DoraTier::Medium => "Medium",
+            DoraTier::Low => "Low",
+        }
+    }
+}
+
+/// Tier boundaries in the metric's configured unit. For metrics where higher is
+/// better a value at or above `elite` is Elite; for inverted metrics (lower is
+/// better) a value at or below it is.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TierThresholds {
+    pub elite: f32,
+    pub high: f32,
+    pub medium: f32,
+}
+
+impl TierThresholds {
+    pub fn classify(&self, value: f32, lower_is_better: bool) -> DoraTier {
+        let reaches = |bound: f32| if lower_is_better { value <= bound } else { value >= bound };
+        if reaches(self.elite) {
+            DoraTier::Elite
+        } else if reaches(self.high) {
+            DoraTier::High
+        } else if reaches(self.medium) {
+            DoraTier::Medium
+        } else {
+            DoraTier::Low
+        }
+    }
+}
+
+/// Published tier thresholds for the four key metrics, in the units of
+/// `DORA_METRIC_CONFIGS` (deployments/day, days, %, days).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DoraBenchmark {
+    pub report: &'static str,
+    pub deployment_frequency: TierThresholds,
+    pub lead_time: TierThresholds,
+    pub change_failure_rate: TierThresholds,
+    pub mttr: TierThresholds,
+}
+
+impl DoraBenchmark {
+    pub fn thresholds(&self, metric_name: &str) -> Option<&TierThresholds> {
+        match metric_name {
+            "deployment_frequency" => Some(&self.deployment_frequency),
+            "lead_time" => Some(&self.lead_time),
+            "change_failure_rate" => Some(&self.change_failure_rate),
+            "mttr" => Some(&self.mttr),
+            _ => None,
+        }
+    }
+
+    /// Tier of a single metric value in the benchmark's unit, or `None` for
+    /// metrics without benchmarks.
+    pub fn classify(&self, metric_name: &str, value: f32) -> Option<DoraTier> {
+        let thresholds = self.thresholds(metric_name)?;
+        let config = builtin_dora_config(metric_name)?;
+        Some(thresholds.classify(value, config.inverted))
+    }
+
+    /// Tier of a metric in any unit, converted to the benchmark's unit first;
+    /// `None` when the units are incompatible.
+    pub fn classify_metric(&self, metric_name: &str, metric: &DoraMetric) -> Option<DoraTier> {
+        let config = builtin_dora_config(metric_name)?;
+        self.classify(metric_name, convert_unit(metric.value, &metric.unit, &config.unit)?)
+    }
+}
+
+/// The built-in configuration of a DORA metric, whose unit the benchmarks use.
+fn builtin_dora_config(metric_name: &str) -> Option<&'static DoraMetricConfig> {
+    DORA_METRIC_CONFIGS
+        .iter()
+        .find(|(name, _)| *name == metric_name)
+        .map(|(_, config)| config)
+}
+
+pub const DORA_BENCHMARKS: &[DoraBenchmark] = &[
+    // Accelerate State of DevOps 2021
+    DoraBenchmark {
+        report: "2021",
+        deployment_frequency: TierThresholds { elite: 1.0, high: 1.0 / 30.0, medium: 1.0 / 182.0 },
+        lead_time: TierThresholds { elite: 1.0 / 24.0, high: 7.0, medium: 182.0 },
+        change_failure_rate: TierThresholds { elite: 15.0, high: 30.0, medium: 30.0 },
+        mttr: TierThresholds { elite: 1.0 / 24.0, high: 1.0, medium: 7.0 },
+    },
+    // Accelerate State of DevOps 2023
+    DoraBenchmark {
+        report: "2023",
+        deployment_frequency: TierThresholds { elite: 1.0, high: 1.0 / 7.0, medium: 1.0 / 30.0 },
+        lead_time: TierThresholds { elite: 1.0, high: 7.0, medium: 30.0 },
+        change_failure_rate: TierThresholds { elite: 5.0, high: 10.0, medium: 15.0 },
+        mttr: TierThresholds { elite: 1.0 / 24.0, high: 1.0, medium: 7.0 },
+    },
+];
+
+pub const DEFAULT_DORA_BENCHMARK: &str = "2023";
+
+pub fn find_dora_benchmark(report: &str) -> Option<&'static DoraBenchmark> {
+    DORA_BENCHMARKS.iter().find(|benchmark| benchmark.report == report)
+}
+
+/// The named benchmark, falling back to `DEFAULT_DORA_BENCHMARK` when the
+/// report is missing or unknown.
+pub fn resolve_dora_benchmark(report: Option<&str>) -> &'static DoraBenchmark {
+    report
+        .and_then(find_dora_benchmark)
+        .or_else(|| find_dora_benchmark(DEFAULT_DORA_BENCHMARK))
+        .unwrap_or(&DORA_BENCHMARKS[0])
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DoraProfile {
+    pub benchmark: String,
+    pub tiers: BTreeMap<String, DoraTier>,
+    /// The median tier, taking the worse of the two middle tiers.
+    pub overall: Option<DoraTier>,
+}
+
+/// Classify translated metrics against a benchmark, converting each from its
+/// own unit, which a catalogue may have changed, to the benchmark's.
+pub fn classify_dora_metrics(metrics: &HashMap<String, DoraMetric>, benchmark: &DoraBenchmark) -> DoraProfile {
+    let tiers: BTreeMap<String, DoraTier> = metrics
+        .iter()
+        .filter_map(|(name, metric)| Some((name.clone(), benchmark.classify_metric(name, metric)?)))
+        .collect();
+
+    let mut sorted: Vec<DoraTier> = tiers.values().copied().collect();
+    sorted.sort();
+    let overall = sorted.get(sorted.len() / 2).copied();
+
+    DoraProfile {
+        benchmark: benchmark.report.to_string(),
+        tiers,
+        overall,
+    }
+}
@@ -0,0 +1,216 @@
+use crate::parser::ParseDiagnostic;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// Wire representation of an [`ApiError`].
+///
+/// Every error leaving the backend (and every validation error surfaced through
+/// WASM) has this shape, so clients can branch on `code` and highlight `field`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ApiErrorBody {
+    pub code: String,
+    pub message: String,
+    #[serde(default)]
+    pub field: Option<String>,
+    #[serde(default)]
+    pub details: Option<Value>,
+    #[serde(default)]
+    pub run_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(into = "ApiErrorBody")]
+pub enum ApiError {
+    /// A request field is missing or out of range. `field` is a dotted path
+    /// into the request, e.g. `dora_metrics.lead_time`.
+    Validation { field: String, message: String },
+    /// Several validation errors collected by `Validate::validate_all`; each is
+    /// listed under `details.errors`.
+    ValidationFailed { errors: Vec<ApiError> },
+    /// The CRT text, or another document in its grammar such as an Evaporating
+    /// Cloud, does not parse; positions are listed under `details.diagnostics`.
+    InvalidCrt {
+        field: String,
+        message: String,
+        diagnostics: Vec<ParseDiagnostic>,
+    },
+    /// The request body could not be decoded into the expected type.
+    InvalidBody { message: String },
+    /// The agent service could not be reached.
+    AgentUnavailable { agent: String, message: String },
+    /// The agent service answered with a non-success status.
+    Agent {
+        agent: String,
+        status: u16,
+        message: String,
+        run_id: Option<String>,
+    },
+    Internal { message: String },
+}
+
+impl ApiError {
+    pub fn validation(field: impl Into<String>, message: impl Into<String>) -> Self {
+        ApiError::Validation {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn internal(message: impl ToString) -> Self {
+        ApiError::Internal {
+            message: message.to_string(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::Validation { .. } => "validation_error",
+            ApiError::ValidationFailed { .. } => "validation_failed",
+            ApiError::InvalidCrt { .. } => "invalid_crt",
+            ApiError::InvalidBody { .. } => "invalid_body",
+            ApiError::AgentUnavailable { .. } => "agent_unavailable",
+            ApiError::Agent { .. } => "agent_error",
+            ApiError::Internal { .. } => "internal_error",
+        }
+    }
+
+    /// HTTP status the backend should answer with.
+    pub fn status(&self) -> u16 {
+        match self {
+            ApiError::Validation { .. }
+            | ApiError::ValidationFailed { .. }
+            | ApiError::InvalidCrt { .. }
+            | ApiError::InvalidBody { .. } => 400,
+            ApiError::AgentUnavailable { .. } => 502,
+            ApiError::Agent { status, .. } => *status,
+            ApiError::Internal { .. } => 500,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::ValidationFailed { errors } => match errors.len() {
+                1 => errors[0].to_string(),
+                n => format!("{n} validation errors"),
+            },
+            ApiError::Validation { message, .. }
+            | ApiError::InvalidCrt { message, .. }
+            | ApiError::InvalidBody { message }
+            | ApiError::AgentUnavailable { message, .. }
+            | ApiError::Agent { message, .. }
+            | ApiError::Internal { message } => message.clone(),
+        }
+    }
+
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            ApiError::Validation { field, .. } | ApiError::InvalidCrt { field, .. } => Some(field),
+            _ => None,
+        }
+    }
+
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            ApiError::ValidationFailed { errors } => {
+                let errors: Vec<ApiErrorBody> = errors.iter().cloned().map(ApiErrorBody::from).collect();
+                Some(serde_json::json!({ "errors": errors }))
+            }
+            ApiError::InvalidCrt { diagnostics, .. } => Some(serde_json::json!({ "diagnostics": diagnostics })),
+            ApiError::AgentUnavailable { agent, .. } => Some(serde_json::json!({ "agent": agent })),
+            ApiError::Agent { agent, status, .. } => {
+                Some(serde_json::json!({ "agent": agent, "upstream_status": status }))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn run_id(&self) -> Option<&str> {
+        match self {
+            ApiError::Agent { run_id, .. } => run_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Prefix the field path of a validation error, used when a request is
+    /// validated as part of an enclosing request (e.g. `original_payload`).
+    pub fn within(self, prefix: &str) -> Self {
+        match self {
+            ApiError::Validation { field, message } => ApiError::Validation {
+                field: format!("{prefix}.{field}"),
+                message,
+            },
+            ApiError::InvalidCrt { field, message, diagnostics } => ApiError::InvalidCrt {
+                field: format!("{prefix}.{field}"),
+                message,
+                diagnostics,
+            },
+            ApiError::ValidationFailed { errors } => ApiError::ValidationFailed {
+                errors: errors.into_iter().map(|e| e.within(prefix)).collect(),
+            },
+            other => other,
+        }
+    }
+}
+
+impl From<ApiError> for ApiErrorBody {
+    fn from(error: ApiError) -> Self {
+        ApiErrorBody {
+            code: error.code().to_string(),
+            message: error.message(),
+            field: error.field().map(str::to_string),
+            details: error.details(),
+            run_id: error.run_id().map(str::to_string),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.field() {
+            Some(field) => write!(f, "{field}: {}", self.message()),
+            None => f.write_str(&self.message()),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation_error_serialises_flat() {
+        let error = ApiError::validation("dora_metrics.lead_time", "Lead time must be between 0 and 1");
+        let json = serde_json::to_value(&error).unwrap();
+
+        assert_eq!(json["code"], "validation_error");
+        assert_eq!(json["field"], "dora_metrics.lead_time");
+        assert_eq!(json["message"], "Lead time must be between 0 and 1");
+        assert!(json["details"].is_null());
+        assert!(json["run_id"].is_null());
+    }
+
+    #[test]
+    fn within_prefixes_field_path() {
+        let error = ApiError::validation("westrum", "out of range").within("original_payload");
+        assert_eq!(error.field(), Some("original_payload.westrum"));
+        assert_eq!(error.status(), 400);
+    }
+
+    #[test]
+    fn validation_failed_lists_every_error() {
+        let error = ApiError::ValidationFailed {
+            errors: vec![
+                ApiError::validation("dora_metrics.mttr", "MTTR must be between 0 and 1"),
+                ApiError::validation("westrum", "Westrum must be between 0 and 7"),
+            ],
+        };
+        let json = serde_json::to_value(&error).unwrap();
+
+        assert_eq!(json["code"], "validation_failed");
+        assert_eq!(json["message"], "2 validation errors");
+        assert_eq!(json["details"]["errors"][1]["field"], "westrum");
+    }
+}
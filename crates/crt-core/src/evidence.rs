@@ -0,0 +1,126 @@
+//! Metric evidence for CRT entities: every entity tagged `[metric: ...]` is
+//! matched with the supplied metric values and the tiers they fall in.
+
+use crate::catalogue::MetricCatalogue;
+use crate::dora::{DoraProfile, DoraTier};
+use crate::parser::CRT;
+use crate::types::DoraMetric;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MetricEvidence {
+    pub metric: String,
+    pub value: f32,
+    pub unit: String,
+    /// Benchmark tier, for the four key DORA metrics.
+    pub tier: Option<DoraTier>,
+    /// Whether the value points to a problem: a Medium or Low tier, or for
+    /// metrics without a benchmark, the worse half of the catalogue range.
+    pub bad: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct EntityEvidence {
+    pub text: String,
+    /// Tagged metrics with a value in the request.
+    pub metrics: Vec<MetricEvidence>,
+    /// Tagged metrics the request did not supply.
+    pub missing: Vec<String>,
+    /// At least one tagged metric is bad, so the data backs the entity.
+    pub supported: bool,
+}
+
+/// Evidence keyed by entity id, for tagged entities only.
+pub type EvidenceMap = BTreeMap<u32, EntityEvidence>;
+
+/// Match the metric tags of `crt` with `metrics` (translated values in their
+/// catalogue units) and the tiers in `profile`.
+pub fn build_evidence_map(
+    crt: &CRT,
+    metrics: &HashMap<String, DoraMetric>,
+    profile: &DoraProfile,
+    catalogue: &MetricCatalogue,
+) -> EvidenceMap {
+    crt.entities
+        .values()
+        .filter(|entity| !entity.metrics.is_empty())
+        .map(|entity| {
+            let (supplied, missing): (Vec<&String>, Vec<&String>) =
+                entity.metrics.iter().partition(|name| metrics.contains_key(name.as_str()));
+            let evidence: Vec<MetricEvidence> = supplied
+                .into_iter()
+                .map(|name| {
+                    let metric = &metrics[name.as_str()];
+                    let tier = profile.tiers.get(name.as_str()).copied();
+                    let bad = match tier {
+                        Some(tier) => tier >= DoraTier::Medium,
+                        None => catalogue.get(name).is_some_and(|config| config.to_slider(metric.value) < 0.5),
+                    };
+                    MetricEvidence {
+                        metric: name.clone(),
+                        value: metric.value,
+                        unit: metric.unit.clone(),
+                        tier,
+                        bad,
+                    }
+                })
+                .collect();
+            let entry = EntityEvidence {
+                text: entity.text.clone(),
+                supported: evidence.iter().any(|metric| metric.bad),
+                metrics: evidence,
+                missing: missing.into_iter().cloned().collect(),
+            };
+            (entity.id, entry)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dora::{classify_dora_metrics, resolve_dora_benchmark};
+    use crate::parser::parse_crt;
+
+    fn metric(value: f32, unit: &str) -> DoraMetric {
+        DoraMetric {
+            value,
+            unit: unit.to_string(),
+        }
+    }
+
+    #[test]
+    fn marks_entities_backed_by_bad_metrics() {
+        let crt = parse_crt(
+            "Entities\n\
+             E1. Delivery is unpredictable [metric: lead_time, mttr]\n\
+             E2. Branches live too long [metric: branch_lifetime]\n\
+             E3. Releases break often [metric: change_failure_rate]\n\
+             E4. Untagged\n\
+             \n\
+             Links\n\
+             L1. E1 → E4\n",
+        )
+        .unwrap();
+        let metrics: HashMap<String, DoraMetric> = [
+            ("lead_time", metric(45.0, "days")),
+            ("change_failure_rate", metric(3.0, "%")),
+            ("branch_lifetime", metric(0.5, "days")),
+        ]
+        .into_iter()
+        .map(|(name, metric)| (name.to_string(), metric))
+        .collect();
+        let profile = classify_dora_metrics(&metrics, resolve_dora_benchmark(None));
+
+        let evidence = build_evidence_map(&crt, &metrics, &profile, &MetricCatalogue::builtin());
+
+        assert_eq!(evidence.keys().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(evidence[&1].metrics[0].tier, Some(DoraTier::Low));
+        assert_eq!(evidence[&1].missing, vec!["mttr"]);
+        assert!(evidence[&1].supported);
+        assert!(!evidence[&2].supported);
+        assert_eq!(evidence[&3].metrics[0].tier, Some(DoraTier::Elite));
+        assert!(!evidence[&3].supported);
+    }
+}
@@ -0,0 +1,569 @@
+//! Future Reality Trees: the injections a team plans, the desirable effects
+//! they lead to and the negative branches they risk. Links may reference the
+//! entities of the CRT being changed, so the tree can be checked against it.
+
+pub use crate::graph::crt_udes;
+use crate::parser::{
+    apply_link_attribute, attribute_pairs, attribute_refs, attribute_scalar, document_text, grammar_diagnostic,
+    line_terminated, parse_statement_line, unknown_attribute, CRTParser, LinkAnnotation, ParseDiagnostic, Rule,
+    Statement, CRT,
+};
+use anyhow::{anyhow, Result};
+use pest::iterators::Pair;
+use pest::Parser;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// A node a link can reference: a declared injection, desirable effect or
+/// negative branch, or an entity of the CRT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum FrtNode {
+    Injection(u32),
+    Effect(u32),
+    Branch(u32),
+    Crt(u32),
+}
+
+impl FrtNode {
+    pub fn prefix(&self) -> char {
+        match self {
+            FrtNode::Injection(_) => 'I',
+            FrtNode::Effect(_) => 'F',
+            FrtNode::Branch(_) => 'N',
+            FrtNode::Crt(_) => 'E',
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            FrtNode::Injection(id) | FrtNode::Effect(id) | FrtNode::Branch(id) | FrtNode::Crt(id) => *id,
+        }
+    }
+}
+
+impl fmt::Display for FrtNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.prefix(), self.id())
+    }
+}
+
+impl FromStr for FrtNode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let unknown = || format!("Unknown node '{s}' (expected I, F, N or E followed by a number)");
+        let mut chars = s.chars();
+        let prefix = chars.next().ok_or_else(unknown)?;
+        let id: u32 = chars.as_str().parse().map_err(|_| unknown())?;
+        match prefix {
+            'I' => Ok(FrtNode::Injection(id)),
+            'F' => Ok(FrtNode::Effect(id)),
+            'N' => Ok(FrtNode::Branch(id)),
+            'E' => Ok(FrtNode::Crt(id)),
+            _ => Err(unknown()),
+        }
+    }
+}
+
+impl From<FrtNode> for String {
+    fn from(node: FrtNode) -> Self {
+        node.to_string()
+    }
+}
+
+impl TryFrom<String> for FrtNode {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// A node in a link, negated when the link refers to a CRT entity no longer holding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct FrtTerm {
+    pub node: FrtNode,
+    pub negated: bool,
+}
+
+impl fmt::Display for FrtTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            write!(f, "NOT ")?;
+        }
+        write!(f, "{}", self.node)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Injection {
+    pub id: u32,
+    pub text: String,
+    pub owner: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DesirableEffect {
+    pub id: u32,
+    pub text: String,
+    /// CRT entities, usually UDEs, the effect replaces.
+    pub resolves: Vec<u32>,
+}
+
+/// An undesirable effect an injection risks causing.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct NegativeBranch {
+    pub id: u32,
+    pub text: String,
+    /// Injections that keep the branch from growing.
+    pub trimmed_by: Vec<u32>,
+}
+
+/// Like a CRT link, but each segment is a flat `AND` of terms.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FrtLink {
+    pub id: u32,
+    pub segments: Vec<Vec<FrtTerm>>,
+    pub confidence: Option<f32>,
+    pub evidence: Option<String>,
+}
+
+/// Keys accepted in the `{...}` block of an injection, effect and branch.
+pub const INJECTION_ATTRIBUTE_KEYS: &[&str] = &["owner"];
+pub const EFFECT_ATTRIBUTE_KEYS: &[&str] = &["resolves"];
+pub const BRANCH_ATTRIBUTE_KEYS: &[&str] = &["trimmed_by"];
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct FRT {
+    pub injections: BTreeMap<u32, Injection>,
+    pub effects: BTreeMap<u32, DesirableEffect>,
+    pub branches: BTreeMap<u32, NegativeBranch>,
+    pub links: BTreeMap<u32, FrtLink>,
+}
+
+impl FRT {
+    /// Whether `node` is declared in the tree; CRT entities are never declared here.
+    pub fn declares(&self, node: FrtNode) -> bool {
+        match node {
+            FrtNode::Injection(id) => self.injections.contains_key(&id),
+            FrtNode::Effect(id) => self.effects.contains_key(&id),
+            FrtNode::Branch(id) => self.branches.contains_key(&id),
+            FrtNode::Crt(_) => false,
+        }
+    }
+
+    /// Cause-effect pairs from consecutive segments of every link.
+    fn steps(&self) -> impl Iterator<Item = (FrtTerm, FrtTerm)> + '_ {
+        self.links.values().flat_map(|link| {
+            link.segments.windows(2).flat_map(|pair| {
+                let (causes, effects) = (pair[0].clone(), pair[1].clone());
+                causes.into_iter().flat_map(move |cause| effects.clone().into_iter().map(move |effect| (cause, effect)))
+            })
+        })
+    }
+
+    /// Every node some link leads to from an injection.
+    pub fn reached_from_injections(&self) -> BTreeSet<FrtNode> {
+        let steps: Vec<(FrtTerm, FrtTerm)> = self.steps().collect();
+        let mut reached = BTreeSet::new();
+        let mut stack: Vec<FrtNode> = self.injections.keys().map(|id| FrtNode::Injection(*id)).collect();
+        while let Some(node) = stack.pop() {
+            for (_, effect) in steps.iter().filter(|(cause, _)| cause.node == node) {
+                if reached.insert(effect.node) {
+                    stack.push(effect.node);
+                }
+            }
+        }
+        reached
+    }
+}
+
+impl fmt::Display for FRT {
+    /// The tree in the grammar `parse_frt` reads.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let refs = |prefix: char, ids: &[u32]| -> String {
+            let refs: Vec<String> = ids.iter().map(|id| format!("{prefix}{id}")).collect();
+            format!("[{}]", refs.join(", "))
+        };
+
+        writeln!(f, "Injections")?;
+        for injection in self.injections.values() {
+            write!(f, "I{}. {}", injection.id, document_text(&injection.text))?;
+            if let Some(owner) = injection.owner.as_deref().map(document_text).filter(|owner| !owner.is_empty()) {
+                write!(f, " {{owner: \"{owner}\"}}")?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\nEffects")?;
+        for effect in self.effects.values() {
+            write!(f, "F{}. {}", effect.id, document_text(&effect.text))?;
+            if !effect.resolves.is_empty() {
+                write!(f, " {{resolves: {}}}", refs('E', &effect.resolves))?;
+            }
+            writeln!(f)?;
+        }
+
+        if !self.branches.is_empty() {
+            writeln!(f, "\nNegative Branches")?;
+            for branch in self.branches.values() {
+                write!(f, "N{}. {}", branch.id, document_text(&branch.text))?;
+                if !branch.trimmed_by.is_empty() {
+                    write!(f, " {{trimmed_by: {}}}", refs('I', &branch.trimmed_by))?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        writeln!(f, "\nLinks")?;
+        for link in self.links.values() {
+            let segments: Vec<String> = link
+                .segments
+                .iter()
+                .map(|terms| terms.iter().map(ToString::to_string).collect::<Vec<_>>().join(" AND "))
+                .collect();
+            write!(f, "L{}. {}", link.id, segments.join(" → "))?;
+            let mut attributes = Vec::new();
+            if let Some(confidence) = link.confidence {
+                attributes.push(format!("confidence: {confidence}"));
+            }
+            if let Some(evidence) = link.evidence.as_deref().map(document_text).filter(|evidence| !evidence.is_empty()) {
+                attributes.push(format!("evidence: \"{evidence}\""));
+            }
+            if !attributes.is_empty() {
+                write!(f, " {{{}}}", attributes.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// How a Future Reality Tree answers the CRT it was built from.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FrtCheck {
+    /// Each CRT UDE with the terms standing for its resolution: effects that
+    /// resolve it, and `NOT E<id>` where a link leads to its negation.
+    pub covered: BTreeMap<u32, Vec<FrtTerm>>,
+    /// CRT UDEs without a corresponding desirable effect.
+    pub uncovered: Vec<u32>,
+    /// Referenced CRT entities that are not in the CRT.
+    pub unknown_entities: Vec<u32>,
+    /// Desirable effects no injection leads to.
+    pub unreached_effects: Vec<u32>,
+    /// Negative branches no link leads to.
+    pub detached_branches: Vec<u32>,
+    /// Negative branches no injection trims.
+    pub untrimmed_branches: Vec<u32>,
+}
+
+impl FrtCheck {
+    /// Every UDE is answered and every reference resolves.
+    pub fn is_complete(&self) -> bool {
+        self.uncovered.is_empty() && self.unknown_entities.is_empty()
+    }
+}
+
+/// Check `frt` against the CRT it changes.
+pub fn check_frt(frt: &FRT, crt: &CRT) -> FrtCheck {
+    let udes = crt_udes(crt);
+    let mut check = FrtCheck::default();
+
+    for effect in frt.effects.values() {
+        for id in effect.resolves.iter().filter(|id| udes.contains(id)) {
+            let term = FrtTerm {
+                node: FrtNode::Effect(effect.id),
+                negated: false,
+            };
+            check.covered.entry(*id).or_default().push(term);
+        }
+    }
+    for (_, effect) in frt.steps() {
+        match effect.node {
+            FrtNode::Crt(id) if effect.negated && udes.contains(&id) => {
+                let terms = check.covered.entry(id).or_default();
+                if !terms.contains(&effect) {
+                    terms.push(effect);
+                }
+            }
+            _ => {}
+        }
+    }
+    check.uncovered = udes.into_iter().filter(|id| !check.covered.contains_key(id)).collect();
+
+    let referenced: BTreeSet<u32> = frt
+        .effects
+        .values()
+        .flat_map(|effect| effect.resolves.iter().copied())
+        .chain(frt.links.values().flat_map(|link| link.segments.iter().flatten()).filter_map(|term| match term.node {
+            FrtNode::Crt(id) => Some(id),
+            _ => None,
+        }))
+        .collect();
+    check.unknown_entities = referenced.into_iter().filter(|id| !crt.entities.contains_key(id)).collect();
+
+    let reached = frt.reached_from_injections();
+    check.unreached_effects = frt.effects.keys().copied().filter(|id| !reached.contains(&FrtNode::Effect(*id))).collect();
+    let targets: BTreeSet<FrtNode> = frt.steps().map(|(_, effect)| effect.node).collect();
+    check.detached_branches = frt.branches.keys().copied().filter(|id| !targets.contains(&FrtNode::Branch(*id))).collect();
+    check.untrimmed_branches = frt
+        .branches
+        .values()
+        .filter(|branch| branch.trimmed_by.is_empty())
+        .map(|branch| branch.id)
+        .collect();
+    check
+}
+
+// ---------- API ----------
+pub fn parse_frt(input: &str) -> Result<FRT> {
+    let source = line_terminated(input);
+    let mut pairs = CRTParser::parse(Rule::frt_file, source.as_ref()).map_err(grammar_diagnostic)?;
+    let file = pairs.next().unwrap();
+
+    let mut frt = FRT::default();
+    for line in file.into_inner() {
+        let at = |e: anyhow::Error| ParseDiagnostic::at(&line, e);
+        match line.as_rule() {
+            Rule::injection_line => {
+                let injection = parse_injection_line(line.clone()).map_err(at)?;
+                let id = injection.id;
+                if frt.injections.insert(id, injection).is_some() {
+                    return Err(at(anyhow!("Duplicate injection I{id}")).into());
+                }
+            }
+            Rule::effect_line => {
+                let effect = parse_effect_line(line.clone()).map_err(at)?;
+                let id = effect.id;
+                if frt.effects.insert(id, effect).is_some() {
+                    return Err(at(anyhow!("Duplicate effect F{id}")).into());
+                }
+            }
+            Rule::branch_line => {
+                let branch = parse_branch_line(line.clone()).map_err(at)?;
+                if let Some(id) = branch.trimmed_by.iter().find(|id| !frt.injections.contains_key(id)) {
+                    return Err(at(anyhow!("Branch N{} is trimmed by undefined injection I{id}", branch.id)).into());
+                }
+                let id = branch.id;
+                if frt.branches.insert(id, branch).is_some() {
+                    return Err(at(anyhow!("Duplicate branch N{id}")).into());
+                }
+            }
+            Rule::frt_link_line => {
+                let link = parse_frt_link_line(line.clone()).map_err(at)?;
+                // nodes are declared before links, so references can be checked here
+                validate_frt_refs(&frt, &link).map_err(at)?;
+                let id = link.id;
+                if frt.links.insert(id, link).is_some() {
+                    return Err(at(anyhow!("Duplicate link L{id}")).into());
+                }
+            }
+            // headings/blanklines/whitespace are already consumed in the grammar
+            _ => {}
+        }
+    }
+    Ok(frt)
+}
+
+// ---------- parsers ----------
+fn parse_injection_line(p: Pair<Rule>) -> Result<Injection> {
+    let Statement { id, text, attributes } = parse_statement_line(p)?;
+    let mut injection = Injection { id, text, owner: None };
+    for (key, value) in attributes {
+        match key.as_str() {
+            "owner" => injection.owner = Some(attribute_scalar(&key, value)?),
+            _ => return Err(unknown_attribute(&format!("injection attribute '{key}'"), INJECTION_ATTRIBUTE_KEYS)),
+        }
+    }
+    Ok(injection)
+}
+
+fn parse_effect_line(p: Pair<Rule>) -> Result<DesirableEffect> {
+    let Statement { id, text, attributes } = parse_statement_line(p)?;
+    let mut effect = DesirableEffect {
+        id,
+        text,
+        resolves: Vec::new(),
+    };
+    for (key, value) in attributes {
+        match key.as_str() {
+            "resolves" => effect.resolves = attribute_refs(&key, value, "E")?,
+            _ => return Err(unknown_attribute(&format!("effect attribute '{key}'"), EFFECT_ATTRIBUTE_KEYS)),
+        }
+    }
+    Ok(effect)
+}
+
+fn parse_branch_line(p: Pair<Rule>) -> Result<NegativeBranch> {
+    let Statement { id, text, attributes } = parse_statement_line(p)?;
+    let mut branch = NegativeBranch {
+        id,
+        text,
+        trimmed_by: Vec::new(),
+    };
+    for (key, value) in attributes {
+        match key.as_str() {
+            "trimmed_by" => branch.trimmed_by = attribute_refs(&key, value, "I")?,
+            _ => return Err(unknown_attribute(&format!("branch attribute '{key}'"), BRANCH_ATTRIBUTE_KEYS)),
+        }
+    }
+    Ok(branch)
+}
+
+fn parse_frt_link_line(p: Pair<Rule>) -> Result<FrtLink> {
+    // frt_link_line = { ws* "L" ID "." ws* junction (ARROW junction)+ attributes? eol }
+    let mut id = None;
+    let mut segments = Vec::new();
+    let mut attributes = Vec::new();
+    for part in p.into_inner() {
+        match part.as_rule() {
+            Rule::ID => id = Some(part.as_str().parse()?),
+            Rule::junction => segments.push(
+                part.into_inner()
+                    .filter(|term| term.as_rule() == Rule::term)
+                    .map(parse_term)
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            Rule::attributes => attribute_pairs(part, &mut attributes)?,
+            _ => {}
+        }
+    }
+    let id = id.ok_or_else(|| anyhow!("Missing link ID"))?;
+    let mut annotation = LinkAnnotation::default();
+    for (key, value) in attributes {
+        apply_link_attribute(&key, value, &mut annotation)?;
+    }
+    Ok(FrtLink {
+        id,
+        segments,
+        confidence: annotation.confidence,
+        evidence: annotation.evidence,
+    })
+}
+
+fn parse_term(p: Pair<Rule>) -> Result<FrtTerm> {
+    // term = { negation? node_ref }
+    let mut negated = false;
+    let mut node = None;
+    for part in p.into_inner() {
+        match part.as_rule() {
+            Rule::negation => negated = true,
+            Rule::node_ref => node = Some(part.as_str().parse::<FrtNode>().map_err(|e| anyhow!(e))?),
+            _ => {}
+        }
+    }
+    let node = node.ok_or_else(|| anyhow!("Missing node reference"))?;
+    if negated && !matches!(node, FrtNode::Crt(_)) {
+        return Err(anyhow!("Only CRT entities can be negated (got NOT {node})"));
+    }
+    Ok(FrtTerm { node, negated })
+}
+
+fn validate_frt_refs(frt: &FRT, link: &FrtLink) -> Result<()> {
+    for term in link.segments.iter().flatten() {
+        let kind = match term.node {
+            FrtNode::Injection(_) => "injection",
+            FrtNode::Effect(_) => "effect",
+            FrtNode::Branch(_) => "branch",
+            // checked against the CRT by `check_frt`
+            FrtNode::Crt(_) => continue,
+        };
+        if !frt.declares(term.node) {
+            return Err(anyhow!("Link L{} references undefined {kind} {}", link.id, term.node));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_crt;
+
+    const CRT_TEXT: &str = "Entities\n\
+        E1. Work is released in large batches\n\
+        E2. Releases are risky\n\
+        E3. Customers wait for fixes {type: ude}\n\
+        E4. Outages last long {type: ude}\n\
+        E5. Teams are stretched {type: ude}\n\
+        \n\
+        Links\n\
+        L1. E1 → E2 → E3\n\
+        L2. E2 → E4\n\
+        L3. E1 → E5\n";
+
+    const FRT_TEXT: &str = "Injections\n\
+        I1. Release every change on its own {owner: platform}\n\
+        I2. Pair on the first releases // until confident\n\
+        \n\
+        Effects\n\
+        F1. Releases are small and safe\n\
+        F2. Fixes reach customers within a day {resolves: E3}\n\
+        F3. Nobody looks after the pipeline {resolves: [E9]}\n\
+        \n\
+        Negative Branches\n\
+        N1. The pipeline becomes a bottleneck {trimmed_by: [I2]}\n\
+        N2. Release notes pile up\n\
+        \n\
+        Links\n\
+        L1. I1 → F1 → F2\n\
+        L2. F1 AND NOT E2 → NOT E4 {confidence: likely}\n\
+        L3. I1 → N1\n";
+
+    #[test]
+    fn parses_and_exports_frt() {
+        let frt = parse_frt(FRT_TEXT).unwrap();
+        assert_eq!(frt.injections[&1].owner.as_deref(), Some("platform"));
+        assert_eq!(frt.injections[&2].text, "Pair on the first releases");
+        assert_eq!(frt.effects[&2].resolves, vec![3]);
+        assert_eq!(frt.branches[&1].trimmed_by, vec![2]);
+        assert_eq!(frt.links[&2].segments[0][1].to_string(), "NOT E2");
+        assert_eq!(frt.links[&2].confidence, Some(0.6));
+
+        assert_eq!(parse_frt(&frt.to_string()).unwrap(), frt);
+    }
+
+    #[test]
+    fn exports_hostile_text_that_parses_back() {
+        let mut frt = parse_frt(FRT_TEXT).unwrap();
+        frt.injections.get_mut(&1).unwrap().text = "Release\nalone {owner: ops} // now".to_string();
+        frt.injections.get_mut(&1).unwrap().owner = Some("the \"platform\" team".to_string());
+        frt.links.get_mut(&1).unwrap().evidence = Some("See https://ci/{run}".to_string());
+
+        let parsed = parse_frt(&frt.to_string()).unwrap();
+        assert_eq!(parsed.injections[&1].text, "Release alone (owner: ops) / / now");
+        assert_eq!(parsed.injections[&1].owner.as_deref(), Some("the 'platform' team"));
+        assert_eq!(parsed.links[&1].evidence.as_deref(), Some("See https:/ /ci/(run)"));
+    }
+
+    #[test]
+    fn rejects_invalid_frts() {
+        let frt = |links: &str| parse_frt(&format!("Injections\nI1. Act\n\nEffects\nF1. Better\n\nLinks\n{links}\n"));
+        assert!(frt("L1. I1 → F2").unwrap_err().to_string().contains("Link L1 references undefined effect F2"));
+        assert!(frt("L1. NOT I1 → F1").unwrap_err().to_string().contains("Only CRT entities can be negated (got NOT I1)"));
+        assert!(frt("L1. I1").is_err());
+
+        let error = parse_frt("Injections\nI1. Act\n\nEffects\nF1. Better {resolves: [F2]}\n\nLinks\n").unwrap_err();
+        assert!(error.to_string().contains("Attribute 'resolves' takes references like E12 (got 'F2')"));
+    }
+
+    #[test]
+    fn checks_frt_against_crt() {
+        let crt = parse_crt(CRT_TEXT).unwrap();
+        let frt = parse_frt(FRT_TEXT).unwrap();
+        let check = check_frt(&frt, &crt);
+
+        assert_eq!(check.covered[&3], vec![FrtTerm { node: FrtNode::Effect(2), negated: false }]);
+        assert_eq!(check.covered[&4][0].to_string(), "NOT E4");
+        assert_eq!(check.uncovered, vec![5]);
+        assert_eq!(check.unknown_entities, vec![9]);
+        assert_eq!(check.unreached_effects, vec![3]);
+        assert_eq!(check.detached_branches, vec![2]);
+        assert_eq!(check.untrimmed_branches, vec![2]);
+        assert!(!check.is_complete());
+    }
+}
@@ -0,0 +1,334 @@
+//! The CRT as a directed graph of entity-to-entity edges, for analyses that
+//! follow causality through the tree.
+
+use crate::parser::{EntityType, Expr, CRT};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, VecDeque};
+
+/// One cause-effect step between two entities, from consecutive segments of
+/// a link. `from_negated` and `to_negated` mark a `NOT` on that end.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Edge {
+    pub from: u32,
+    pub to: u32,
+    pub link: u32,
+    /// Index in the link of the segment the step leaves from. Steps sharing a
+    /// link and segment leave from one junction, and need all its inputs.
+    pub segment: usize,
+    pub confidence: f32,
+    pub from_negated: bool,
+    pub to_negated: bool,
+}
+
+/// A chain of entities with the links between them. `confidence` is the
+/// product of the link confidences along the chain.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CausalPath {
+    pub entities: Vec<u32>,
+    pub links: Vec<u32>,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CausalGraph {
+    pub nodes: BTreeSet<u32>,
+    pub edges: Vec<Edge>,
+    outgoing: BTreeMap<u32, Vec<usize>>,
+    incoming: BTreeMap<u32, Vec<usize>>,
+}
+
+/// Entity references in `expr`, with whether each sits under an odd number of `NOT`s.
+pub(crate) fn leaves(expr: &Expr, negated: bool, out: &mut Vec<(u32, bool)>) {
+    match expr {
+        Expr::EntityRef(id) => out.push((*id, negated)),
+        Expr::Not(inner) => leaves(inner, !negated, out),
+        Expr::And(items) => items.iter().for_each(|item| leaves(item, negated, out)),
+    }
+}
+
+impl CausalGraph {
+    pub fn from_crt(crt: &CRT) -> Self {
+        let mut graph = CausalGraph {
+            nodes: crt.entities.keys().copied().collect(),
+            ..Default::default()
+        };
+        for link in crt.links.values() {
+            for (segment, pair) in link.segments.windows(2).enumerate() {
+                let (mut sources, mut targets) = (Vec::new(), Vec::new());
+                leaves(&pair[0], false, &mut sources);
+                leaves(&pair[1], false, &mut targets);
+                for &(from, from_negated) in &sources {
+                    for &(to, to_negated) in &targets {
+                        graph.add_edge(Edge {
+                            from,
+                            to,
+                            link: link.id,
+                            segment,
+                            confidence: link.confidence_or_default(),
+                            from_negated,
+                            to_negated,
+                        });
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    fn add_edge(&mut self, edge: Edge) {
+        let index = self.edges.len();
+        self.nodes.extend([edge.from, edge.to]);
+        self.outgoing.entry(edge.from).or_default().push(index);
+        self.incoming.entry(edge.to).or_default().push(index);
+        self.edges.push(edge);
+    }
+
+    pub fn successors(&self, id: u32) -> impl Iterator<Item = &Edge> {
+        self.outgoing.get(&id).into_iter().flatten().map(|index| &self.edges[*index])
+    }
+
+    pub fn predecessors(&self, id: u32) -> impl Iterator<Item = &Edge> {
+        self.incoming.get(&id).into_iter().flatten().map(|index| &self.edges[*index])
+    }
+
+    /// Entities nothing leads to: the candidate root causes.
+    pub fn sources(&self) -> Vec<u32> {
+        self.nodes.iter().copied().filter(|id| !self.incoming.contains_key(id)).collect()
+    }
+
+    /// Entities that lead nowhere: the final effects, usually the UDEs.
+    pub fn sinks(&self) -> Vec<u32> {
+        self.nodes.iter().copied().filter(|id| !self.outgoing.contains_key(id)).collect()
+    }
+
+    /// Every entity reachable from `from` by following links, excluding
+    /// `from` itself unless it lies on a cycle.
+    pub fn reachable(&self, from: u32) -> BTreeSet<u32> {
+        let mut reached = BTreeSet::new();
+        let mut stack = vec![from];
+        while let Some(node) = stack.pop() {
+            for edge in self.successors(node) {
+                if reached.insert(edge.to) {
+                    stack.push(edge.to);
+                }
+            }
+        }
+        reached
+    }
+
+    /// The sinks reachable from `from`: the final effects it leads to.
+    pub fn effects_of(&self, from: u32) -> Vec<u32> {
+        let reached = self.reachable(from);
+        self.sinks().into_iter().filter(|sink| reached.contains(sink)).collect()
+    }
+
+    /// Entities a step leads to from `id`, each once however many links join them.
+    pub fn neighbours_out(&self, id: u32) -> BTreeSet<u32> {
+        self.successors(id).map(|edge| edge.to).collect()
+    }
+
+    /// Entities leading to `id` in one step, each once.
+    pub fn neighbours_in(&self, id: u32) -> BTreeSet<u32> {
+        self.predecessors(id).map(|edge| edge.from).collect()
+    }
+
+    /// Betweenness centrality of every entity: the share of shortest chains
+    /// between two other entities that pass through it, 0–1. Chains count
+    /// steps, not confidence, and ties split the credit.
+    pub fn betweenness(&self) -> BTreeMap<u32, f32> {
+        // Brandes' algorithm, one breadth-first search per source
+        let mut centrality: BTreeMap<u32, f64> = self.nodes.iter().map(|id| (*id, 0.0)).collect();
+        for &source in &self.nodes {
+            let mut order = Vec::new();
+            let mut previous: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+            let mut paths = BTreeMap::from([(source, 1.0_f64)]);
+            let mut distance = BTreeMap::from([(source, 0_usize)]);
+            let mut queue = VecDeque::from([source]);
+            while let Some(node) = queue.pop_front() {
+                order.push(node);
+                for next in self.neighbours_out(node) {
+                    if !distance.contains_key(&next) {
+                        distance.insert(next, distance[&node] + 1);
+                        queue.push_back(next);
+                    }
+                    if distance[&next] == distance[&node] + 1 {
+                        *paths.entry(next).or_default() += paths[&node];
+                        previous.entry(next).or_default().push(node);
+                    }
+                }
+            }
+
+            let mut dependency: BTreeMap<u32, f64> = BTreeMap::new();
+            while let Some(node) = order.pop() {
+                let through = dependency.get(&node).copied().unwrap_or(0.0);
+                for &before in previous.get(&node).into_iter().flatten() {
+                    *dependency.entry(before).or_default() += paths[&before] / paths[&node] * (1.0 + through);
+                }
+                if node != source {
+                    *centrality.get_mut(&node).unwrap() += through;
+                }
+            }
+        }
+
+        let n = self.nodes.len() as f64;
+        let pairs = if n > 2.0 { (n - 1.0) * (n - 2.0) } else { 1.0 };
+        centrality.into_iter().map(|(id, value)| (id, (value / pairs) as f32)).collect()
+    }
+
+    /// The most confident path from any of `from` to `to`, or `None` when `to`
+    /// cannot be reached through links with a positive confidence.
+    pub fn strongest_path(&self, from: &[u32], to: u32) -> Option<CausalPath> {
+        // Dijkstra over -ln(confidence): maximising a product of factors in 0..=1
+        let mut cost: BTreeMap<u32, f64> = BTreeMap::new();
+        let mut previous: BTreeMap<u32, usize> = BTreeMap::new();
+        let mut queue = BinaryHeap::new();
+        for &start in from {
+            cost.insert(start, 0.0);
+            queue.push(Visit { cost: 0.0, node: start });
+        }
+
+        while let Some(Visit { cost: reached, node }) = queue.pop() {
+            if node == to {
+                break;
+            }
+            if reached > cost.get(&node).copied().unwrap_or(f64::INFINITY) {
+                continue;
+            }
+            for &index in self.outgoing.get(&node).into_iter().flatten() {
+                let edge = &self.edges[index];
+                if edge.confidence <= 0.0 {
+                    continue;
+                }
+                let next = reached - f64::from(edge.confidence).ln();
+                if next < cost.get(&edge.to).copied().unwrap_or(f64::INFINITY) {
+                    cost.insert(edge.to, next);
+                    previous.insert(edge.to, index);
+                    queue.push(Visit { cost: next, node: edge.to });
+                }
+            }
+        }
+
+        let total = *cost.get(&to)?;
+        let (mut entities, mut links) = (vec![to], Vec::new());
+        let mut node = to;
+        // start nodes cost nothing, so they never gain a predecessor
+        while let Some(&index) = previous.get(&node) {
+            let edge = &self.edges[index];
+            links.push(edge.link);
+            entities.push(edge.from);
+            node = edge.from;
+        }
+        entities.reverse();
+        links.reverse();
+        Some(CausalPath {
+            entities,
+            links,
+            confidence: (-total).exp() as f32,
+        })
+    }
+
+    /// For every sink, its most confident path from any source. Sinks only
+    /// reachable through cycles (with no source upstream) are left out.
+    pub fn strongest_paths_to_sinks(&self) -> Vec<CausalPath> {
+        let sources = self.sources();
+        self.sinks()
+            .into_iter()
+            .filter(|sink| !sources.contains(sink))
+            .filter_map(|sink| self.strongest_path(&sources, sink))
+            .collect()
+    }
+}
+
+/// The UDEs of `crt`: the entities typed `ude`, or when none is typed, the
+/// final effects of the tree.
+pub fn crt_udes(crt: &CRT) -> Vec<u32> {
+    let typed: Vec<u32> = crt
+        .entities
+        .values()
+        .filter(|entity| entity.attributes.kind == Some(EntityType::Ude))
+        .map(|entity| entity.id)
+        .collect();
+    if !typed.is_empty() {
+        return typed;
+    }
+    let graph = CausalGraph::from_crt(crt);
+    let sources = graph.sources();
+    graph.sinks().into_iter().filter(|sink| !sources.contains(sink)).collect()
+}
+
+/// Queue entry ordered so the `BinaryHeap` pops the lowest cost first.
+#[derive(Debug, PartialEq)]
+struct Visit {
+    cost: f64,
+    node: u32,
+}
+
+impl Eq for Visit {}
+
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then_with(|| self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_crt;
+
+    const TREE: &str = "Entities\n\
+        E1. Root\n\
+        E2. Firm step\n\
+        E3. Hypothetical step\n\
+        E4. Effect\n\
+        E5. Other root\n\
+        \n\
+        Links\n\
+        L1. E1 → E2 {confidence: 0.9}\n\
+        L2. E2 → E4 {confidence: 0.8}\n\
+        L3. E1 → E3\n\
+        L4. E3 → E4 {confidence: hypothesis}\n\
+        L5. E5 AND E3 → E4 {confidence: 0.1}\n";
+
+    #[test]
+    fn builds_edges_from_link_segments() {
+        let graph = CausalGraph::from_crt(&parse_crt(TREE).unwrap());
+        assert_eq!(graph.sources(), vec![1, 5]);
+        assert_eq!(graph.sinks(), vec![4]);
+        assert_eq!(graph.successors(1).map(|edge| edge.to).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(graph.predecessors(4).count(), 4);
+        assert_eq!(graph.reachable(1), BTreeSet::from([2, 3, 4]));
+    }
+
+    #[test]
+    fn splits_betweenness_between_shortest_chains() {
+        let graph = CausalGraph::from_crt(&parse_crt(TREE).unwrap());
+        let betweenness = graph.betweenness();
+        // E1 → E4 runs through E2 or E3; five entities leave 4 × 3 ordered pairs
+        assert!((betweenness[&2] - 0.5 / 12.0).abs() < 1e-6);
+        assert_eq!(betweenness[&2], betweenness[&3]);
+        assert_eq!((betweenness[&1], betweenness[&4]), (0.0, 0.0));
+        assert_eq!(graph.neighbours_in(4), BTreeSet::from([2, 3, 5]));
+    }
+
+    #[test]
+    fn strongest_path_multiplies_confidence() {
+        let graph = CausalGraph::from_crt(&parse_crt(TREE).unwrap());
+        let path = graph.strongest_path(&[1], 4).unwrap();
+        assert_eq!(path.entities, vec![1, 2, 4]);
+        assert_eq!(path.links, vec![1, 2]);
+        assert!((path.confidence - 0.72).abs() < 1e-6);
+
+        let paths = graph.strongest_paths_to_sinks();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].entities, vec![1, 2, 4]);
+        assert!(graph.strongest_path(&[2], 1).is_none());
+    }
+}
@@ -0,0 +1,112 @@
+//! Leverage ranking: how much of the tree each entity drives, measured from
+//! its position in the causal graph, so leverage points can be grounded in
+//! structure rather than asserted.
+
+use crate::graph::{crt_udes, CausalGraph};
+use crate::parser::CRT;
+use serde::{Deserialize, Serialize};
+
+/// Weights of the composite score. Each measure is scaled to 0–1 first, so
+/// the score is too.
+pub const UDE_REACH_WEIGHT: f32 = 0.45;
+pub const BETWEENNESS_WEIGHT: f32 = 0.2;
+pub const OUT_DEGREE_WEIGHT: f32 = 0.15;
+pub const LOOP_WEIGHT: f32 = 0.1;
+pub const UPSTREAM_WEIGHT: f32 = 0.1;
+
+/// The structural measures of one entity.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct EntityLeverage {
+    pub id: u32,
+    /// Entities leading to this one in a single step.
+    pub in_degree: usize,
+    /// Entities this one leads to in a single step.
+    pub out_degree: usize,
+    /// Share of shortest chains between other entities through this one, 0–1.
+    pub betweenness: f32,
+    /// UDEs this entity leads to, directly or not.
+    pub udes_reached: Vec<u32>,
+    /// Whether the entity lies on a feedback loop.
+    pub in_loop: bool,
+    /// Weighted sum of the share of UDEs reached, betweenness and out-degree
+    /// (both relative to the tree's highest), loop membership, and
+    /// `1 / (1 + in_degree)` for sitting upstream.
+    pub score: f32,
+}
+
+/// Every entity of `crt` with its measures, highest score first; ties go to
+/// the lowest id.
+pub fn rank_leverage(crt: &CRT) -> Vec<EntityLeverage> {
+    let graph = CausalGraph::from_crt(crt);
+    let udes = crt_udes(crt);
+    let betweenness = graph.betweenness();
+
+    let mut ranking: Vec<EntityLeverage> = crt
+        .entities
+        .keys()
+        .map(|&id| {
+            let reached = graph.reachable(id);
+            EntityLeverage {
+                id,
+                in_degree: graph.neighbours_in(id).len(),
+                out_degree: graph.neighbours_out(id).len(),
+                betweenness: betweenness.get(&id).copied().unwrap_or(0.0),
+                udes_reached: udes.iter().copied().filter(|ude| *ude != id && reached.contains(ude)).collect(),
+                in_loop: reached.contains(&id),
+                score: 0.0,
+            }
+        })
+        .collect();
+
+    let share = |value: f32, max: f32| if max > 0.0 { value / max } else { 0.0 };
+    let max_betweenness = ranking.iter().map(|entity| entity.betweenness).fold(0.0, f32::max);
+    let max_out_degree = ranking.iter().map(|entity| entity.out_degree).max().unwrap_or(0) as f32;
+    for entity in &mut ranking {
+        entity.score = UDE_REACH_WEIGHT * share(entity.udes_reached.len() as f32, udes.len() as f32)
+            + BETWEENNESS_WEIGHT * share(entity.betweenness, max_betweenness)
+            + OUT_DEGREE_WEIGHT * share(entity.out_degree as f32, max_out_degree)
+            + LOOP_WEIGHT * f32::from(u8::from(entity.in_loop))
+            + UPSTREAM_WEIGHT / (1.0 + entity.in_degree as f32);
+    }
+    ranking.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+    ranking
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_crt;
+
+    #[test]
+    fn ranks_entities_by_structural_leverage() {
+        let crt = parse_crt(
+            "Entities\n\
+             E1. Changes need manual approval {type: root_cause}\n\
+             E2. Releases batch up\n\
+             E3. Audits demand sign-off\n\
+             E4. Failures are hard to trace {type: ude}\n\
+             E5. Customers wait for fixes {type: ude}\n\
+             E6. Support escalates\n\
+             \n\
+             Links\n\
+             L1. E1 → E2 → E4\n\
+             L2. E2 → E5 → E6 → E5\n\
+             L3. E3 → E4\n",
+        )
+        .unwrap();
+
+        let ranking = rank_leverage(&crt);
+        let order: Vec<u32> = ranking.iter().map(|entity| entity.id).collect();
+        assert_eq!(order, vec![2, 1, 6, 3, 5, 4]);
+
+        let hub = &ranking[0];
+        assert_eq!((hub.in_degree, hub.out_degree), (1, 2));
+        assert_eq!(hub.udes_reached, vec![4, 5]);
+        assert!((hub.betweenness - 3.0 / 20.0).abs() < 1e-6);
+        assert!((hub.score - 0.85).abs() < 1e-6);
+
+        let loop_member = ranking.iter().find(|entity| entity.id == 5).unwrap();
+        assert!(loop_member.in_loop);
+        assert!(loop_member.udes_reached.is_empty());
+    }
+}
@@ -1,7 +1,22 @@
+pub mod error;
 pub mod types;
+pub mod cloud;
+pub mod frt;
+pub mod negation;
+pub mod cutsets;
+pub mod simulation;
+pub mod prt;
+pub mod transition;
 pub mod dora;
+pub mod evidence;
+pub mod graph;
+pub mod leverage;
+pub mod paths;
+pub mod trend;
+pub mod catalogue;
 pub mod validation;
 pub mod parser;
+pub mod westrum;
 
 #[cfg(feature = "wasm")]
 pub mod wasm;
@@ -27,17 +42,17 @@ mod tests {
         assert_eq!(result_1.value, 10.0);
         assert_eq!(result_1.unit, "deployments/day");
 
-        // Test specific points
+        // Test specific points (logarithmic - one decade per quarter)
         let result_025 = config.translate(0.25);
-        assert_eq!(result_025.value, 2.501); // 2.501 rounded to 2 decimal places
+        assert_eq!(result_025.value, 0.01);
         assert_eq!(result_025.unit, "deployments/day");
 
         let result_05 = config.translate(0.5);
-        assert_eq!(result_05.value, 5.0);
+        assert_eq!(result_05.value, 0.1);
         assert_eq!(result_05.unit, "deployments/day");
 
         let result_067 = config.translate(0.67);
-        assert_eq!(result_067.value, 6.7);
+        assert_eq!(result_067.value, 0.479);
         assert_eq!(result_067.unit, "deployments/day");
     }
 
@@ -58,17 +73,21 @@ mod tests {
         assert_eq!(result_1.value, 0.04);
         assert_eq!(result_1.unit, "days");
 
-        // Test specific points
+        // Test specific points (piecewise at the 1 month / 1 week / 1 day tier boundaries)
         let result_025 = config.translate(0.25);
-        assert_eq!(result_025.value, 45.01);
+        assert_eq!(result_025.value, 30.0);
         assert_eq!(result_025.unit, "days");
 
         let result_05 = config.translate(0.5);
-        assert_eq!(result_05.value, 30.02);
+        assert_eq!(result_05.value, 7.0);
         assert_eq!(result_05.unit, "days");
 
+        let result_075 = config.translate(0.75);
+        assert_eq!(result_075.value, 1.0);
+        assert_eq!(result_075.unit, "days");
+
         let result_067 = config.translate(0.67);
-        assert_eq!(result_067.value, 19.827); // 19.827 rounded to 2 decimal places
+        assert_eq!(result_067.value, 2.92);
         assert_eq!(result_067.unit, "days");
     }
 
@@ -120,20 +139,20 @@ mod tests {
         assert_eq!(result_0.unit, "days");
 
         let result_1 = config.translate(1.0);
-        assert_eq!(result_1.value, 0.012); // 0.012 rounded to 2 decimal places
+        assert_eq!(result_1.value, 0.013); // 0.0125 rounded to 3 decimal places
         assert_eq!(result_1.unit, "days");
 
-        // Test specific points
+        // Test specific points (piecewise at the 1 week / 1 day / 1 hour tier boundaries)
         let result_025 = config.translate(0.25);
-        assert_eq!(result_025.value, 10.503); // 10.503 rounded to 2 decimal places
+        assert_eq!(result_025.value, 7.0);
         assert_eq!(result_025.unit, "days");
 
         let result_05 = config.translate(0.5);
-        assert_eq!(result_05.value, 7.006); // 7.006 rounded to 2 decimal places
+        assert_eq!(result_05.value, 1.0);
         assert_eq!(result_05.unit, "days");
 
         let result_067 = config.translate(0.67);
-        assert_eq!(result_067.value, 4.628); // 4.628 rounded to 2 decimal places
+        assert_eq!(result_067.value, 0.348);
         assert_eq!(result_067.unit, "days");
     }
 
@@ -199,6 +218,62 @@ mod tests {
         assert_eq!(result_067.unit, "days");
     }
 
+    #[test]
+    fn test_pbis_translation() {
+        let config = DORA_METRIC_CONFIGS
+            .iter()
+            .find(|(name, _)| *name == "pbis_delivered_per_sprint_per_team")
+            .map(|(_, config)| config)
+            .unwrap();
+
+        // Test boundary values
+        let result_0 = config.translate(0.0);
+        assert_eq!(result_0.value, 0.0);
+        assert_eq!(result_0.unit, "PBIs/sprint/team");
+
+        let result_1 = config.translate(1.0);
+        assert_eq!(result_1.value, 20.0);
+        assert_eq!(result_1.unit, "PBIs/sprint/team");
+
+        // Test specific points
+        let result_025 = config.translate(0.25);
+        assert_eq!(result_025.value, 5.0);
+        assert_eq!(result_025.unit, "PBIs/sprint/team");
+
+        let result_05 = config.translate(0.5);
+        assert_eq!(result_05.value, 10.0);
+        assert_eq!(result_05.unit, "PBIs/sprint/team");
+
+        let result_067 = config.translate(0.67);
+        assert_eq!(result_067.value, 13.4);
+        assert_eq!(result_067.unit, "PBIs/sprint/team");
+    }
+
+    #[test]
+    fn test_to_slider_inverts_translation() {
+        for (metric_name, config) in DORA_METRIC_CONFIGS {
+            for slider in [0.0, 0.25, 0.5, 0.67, 1.0] {
+                let value = config.translate(slider).value;
+                let back = config.to_slider(value);
+                // values are rounded to 3 decimals, which matters on steep segments
+                assert!((back - slider).abs() < 0.01,
+                    "Metric {}: slider {} translated to {} but inverted to {}",
+                    metric_name, slider, value, back);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unit_conversion() {
+        assert_eq!(convert_unit(3.5, "days", "hours"), Some(84.0));
+        assert_eq!(convert_unit(2.0, "weeks", "days"), Some(14.0));
+        assert_eq!(convert_unit(14.0, "deployments/week", "deployments/day"), Some(2.0));
+        assert_eq!(convert_unit(1.0, "commits/hour per developer", "commits/day per developer"), Some(24.0));
+        assert_eq!(convert_unit(5.0, "%", "%"), Some(5.0));
+        assert_eq!(convert_unit(1.0, "days", "%"), None);
+        assert_eq!(convert_unit(1.0, "commits/day", "deployments/day"), None);
+    }
+
     #[test]
     fn test_translation_consistency() {
         // Test that all metrics have consistent behavior
@@ -225,7 +300,10 @@ mod tests {
                 assert_eq!(result_1.value, config.max_value);
             }
 
-            // Test that 0.5 gives the middle value
+            // Test that 0.5 gives the middle value (linear scales only)
+            if config.scale != Scale::Linear {
+                continue;
+            }
             let result_05 = config.translate(0.5);
             let expected_middle = (config.min_value + config.max_value) / 2.0;
             assert!((result_05.value - expected_middle).abs() < 0.05, 
@@ -233,4 +311,68 @@ mod tests {
                 metric_name, expected_middle, result_05.value);
         }
     }
+
+    #[test]
+    fn test_dora_tier_classification() {
+        let benchmark = find_dora_benchmark("2023").unwrap();
+
+        // Higher is better for deployment frequency
+        assert_eq!(benchmark.classify("deployment_frequency", 3.0), Some(DoraTier::Elite));
+        assert_eq!(benchmark.classify("deployment_frequency", 0.2), Some(DoraTier::High));
+        assert_eq!(benchmark.classify("deployment_frequency", 0.05), Some(DoraTier::Medium));
+        assert_eq!(benchmark.classify("deployment_frequency", 0.01), Some(DoraTier::Low));
+
+        // Lower is better for inverted metrics
+        assert_eq!(benchmark.classify("lead_time", 0.5), Some(DoraTier::Elite));
+        assert_eq!(benchmark.classify("lead_time", 7.0), Some(DoraTier::High));
+        assert_eq!(benchmark.classify("lead_time", 45.0), Some(DoraTier::Low));
+        assert_eq!(benchmark.classify("change_failure_rate", 12.0), Some(DoraTier::Medium));
+        assert_eq!(benchmark.classify("mttr", 0.5), Some(DoraTier::High));
+
+        // Thresholds differ per report
+        let benchmark_2021 = find_dora_benchmark("2021").unwrap();
+        assert_eq!(benchmark_2021.classify("change_failure_rate", 12.0), Some(DoraTier::Elite));
+
+        assert_eq!(benchmark.classify("commit_frequency", 5.0), None);
+        assert!(find_dora_benchmark("1999").is_none());
+    }
+
+    #[test]
+    fn test_dora_profile_takes_worse_median() {
+        let metric = |value: f32, unit: &str| crate::types::DoraMetric {
+            value,
+            unit: unit.to_string(),
+        };
+        let metrics: std::collections::HashMap<String, crate::types::DoraMetric> = [
+            ("deployment_frequency", metric(3.0, "deployments/day")),
+            ("lead_time", metric(0.5, "days")),
+            ("change_failure_rate", metric(12.0, "%")),
+            ("mttr", metric(30.0, "days")),
+        ]
+        .into_iter()
+        .map(|(name, metric)| (name.to_string(), metric))
+        .collect();
+
+        let profile = classify_dora_metrics(&metrics, find_dora_benchmark("2023").unwrap());
+        assert_eq!(profile.tiers["mttr"], DoraTier::Low);
+        assert_eq!(profile.overall, Some(DoraTier::Medium));
+    }
+
+    #[test]
+    fn test_dora_classification_converts_units() {
+        let benchmark = find_dora_benchmark("2023").unwrap();
+        let metric = |value: f32, unit: &str| crate::types::DoraMetric {
+            value,
+            unit: unit.to_string(),
+        };
+
+        // 84 hours is 3.5 days, High; read as days it would be Low
+        assert_eq!(benchmark.classify_metric("lead_time", &metric(84.0, "hours")), Some(DoraTier::High));
+        assert_eq!(benchmark.classify_metric("mttr", &metric(30.0, "minutes")), Some(DoraTier::Elite));
+        assert_eq!(benchmark.classify_metric("lead_time", &metric(3.0, "%")), None);
+
+        let metrics = [("lead_time".to_string(), metric(84.0, "hours"))].into_iter().collect();
+        let profile = classify_dora_metrics(&metrics, benchmark);
+        assert_eq!(profile.tiers["lead_time"], DoraTier::High);
+    }
 }
@@ -0,0 +1,217 @@
+//! Negating a CRT: given the root causes a team injects against, which
+//! entities stop holding, laid out as the `NOT` chains of a Future Reality
+//! Tree skeleton.
+
+use crate::frt::{FrtLink, FrtNode, FrtTerm, Injection, FRT};
+use crate::graph::{crt_udes, leaves};
+use crate::parser::CRT;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// What injecting against some root causes of a CRT neutralises.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FrtSkeleton {
+    /// One injection per root cause, and links from each to the negation of
+    /// every entity that no longer holds.
+    pub frt: FRT,
+    /// Entities that no longer hold, the injected root causes included.
+    pub neutralised: Vec<u32>,
+    pub neutralised_udes: Vec<u32>,
+    pub remaining_udes: Vec<u32>,
+}
+
+/// The causes of each entity: one junction of entities per link step leading
+/// to it, any one of which is sufficient. Negated terms are conditions rather
+/// than causes, so they are left out, as are steps into a negated entity.
+pub(crate) fn junctions(crt: &CRT) -> BTreeMap<u32, Vec<Vec<u32>>> {
+    let mut causes: BTreeMap<u32, Vec<Vec<u32>>> = BTreeMap::new();
+    for link in crt.links.values() {
+        for pair in link.segments.windows(2) {
+            let (mut sources, mut targets) = (Vec::new(), Vec::new());
+            leaves(&pair[0], false, &mut sources);
+            leaves(&pair[1], false, &mut targets);
+            let junction: Vec<u32> = sources.iter().filter(|(_, negated)| !negated).map(|(id, _)| *id).collect();
+            for (target, _) in targets.iter().filter(|(_, negated)| !negated) {
+                causes.entry(*target).or_default().push(junction.clone());
+            }
+        }
+    }
+    causes
+}
+
+/// Negate `crt` with `injected` root causes removed. An entity stops holding
+/// when every junction leading to it has lost a cause, so a loop stops once
+/// nothing outside it sustains it. Entities with no causes hold unless injected.
+/// `simulate` reads loops the same way, but also follows `NOT` terms.
+pub fn negate_crt(crt: &CRT, injected: &[u32]) -> Result<FrtSkeleton> {
+    if let Some(id) = injected.iter().find(|id| !crt.entities.contains_key(id)) {
+        return Err(anyhow!("Entity E{id} is not in the CRT"));
+    }
+    let injected: BTreeSet<u32> = injected.iter().copied().collect();
+    let causes = junctions(crt);
+    let neutralised = neutralised(crt, &causes, &injected);
+
+    let (neutralised_udes, remaining_udes) = crt_udes(crt).into_iter().partition(|id| neutralised.contains(id));
+    Ok(FrtSkeleton {
+        frt: skeleton_frt(crt, &injected, &neutralised, &causes),
+        neutralised,
+        neutralised_udes,
+        remaining_udes,
+    })
+}
+
+/// The entities that no longer hold once `injected` are removed, themselves
+/// included, as `negate_crt` describes.
+pub(crate) fn neutralised(crt: &CRT, causes: &BTreeMap<u32, Vec<Vec<u32>>>, injected: &BTreeSet<u32>) -> Vec<u32> {
+    // only entities downstream of an injection can change
+    let mut affected = injected.clone();
+    let mut stack: Vec<u32> = injected.iter().copied().collect();
+    while let Some(id) = stack.pop() {
+        for (target, junctions) in causes {
+            if junctions.iter().any(|junction| junction.contains(&id)) && affected.insert(*target) {
+                stack.push(*target);
+            }
+        }
+    }
+
+    let mut holds: BTreeSet<u32> = crt.entities.keys().copied().filter(|id| !affected.contains(id)).collect();
+    loop {
+        let revived: Vec<u32> = affected
+            .iter()
+            .copied()
+            .filter(|id| !injected.contains(id) && !holds.contains(id))
+            .filter(|id| causes[id].iter().any(|junction| junction.iter().all(|cause| holds.contains(cause))))
+            .collect();
+        if revived.is_empty() {
+            break;
+        }
+        holds.extend(revived);
+    }
+    affected.into_iter().filter(|id| !holds.contains(id)).collect()
+}
+
+fn not(id: u32) -> FrtTerm {
+    FrtTerm {
+        node: FrtNode::Crt(id),
+        negated: true,
+    }
+}
+
+/// Each neutralised entity with the terms that negate it: its injection, or
+/// one lost cause from every junction, joined into chains where an entity's
+/// negation leads to exactly one other.
+fn skeleton_frt(crt: &CRT, injected: &BTreeSet<u32>, neutralised: &[u32], causes: &BTreeMap<u32, Vec<Vec<u32>>>) -> FRT {
+    let mut frt = FRT::default();
+    let mut steps: BTreeMap<u32, Vec<FrtTerm>> = BTreeMap::new();
+    for (index, &id) in injected.iter().enumerate() {
+        let injection = Injection {
+            id: index as u32 + 1,
+            text: format!("Injection removing E{id}: {}", crt.entities[&id].text),
+            owner: None,
+        };
+        steps.insert(id, vec![FrtTerm { node: FrtNode::Injection(injection.id), negated: false }]);
+        frt.injections.insert(injection.id, injection);
+    }
+    for &id in neutralised.iter().filter(|id| !injected.contains(id)) {
+        let lost: BTreeSet<u32> = causes[&id]
+            .iter()
+            .filter_map(|junction| junction.iter().copied().filter(|cause| neutralised.contains(cause)).min())
+            .collect();
+        steps.insert(id, lost.into_iter().map(not).collect());
+    }
+
+    let mut consumers: BTreeMap<u32, usize> = BTreeMap::new();
+    for terms in steps.values() {
+        for term in terms.iter().filter(|term| term.negated) {
+            *consumers.entry(term.node.id()).or_default() += 1;
+        }
+    }
+    let next: BTreeMap<u32, u32> = steps
+        .iter()
+        .filter_map(|(&id, terms)| match terms.as_slice() {
+            [term] if term.negated && consumers[&term.node.id()] == 1 => Some((term.node.id(), id)),
+            _ => None,
+        })
+        .collect();
+    let continued: BTreeSet<u32> = next.values().copied().collect();
+
+    // chain heads first; whatever is left sits on a loop of single steps
+    let mut visited = BTreeSet::new();
+    let heads = steps.keys().filter(|id| !continued.contains(id)).chain(steps.keys());
+    for &head in heads {
+        if !visited.insert(head) {
+            continue;
+        }
+        let mut segments = vec![steps[&head].clone(), vec![not(head)]];
+        let mut at = head;
+        while let Some(&id) = next.get(&at) {
+            if !visited.insert(id) {
+                break;
+            }
+            segments.push(vec![not(id)]);
+            at = id;
+        }
+        let id = frt.links.len() as u32 + 1;
+        frt.links.insert(id, FrtLink { id, segments, confidence: None, evidence: None });
+    }
+    frt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frt::{check_frt, parse_frt};
+    use crate::parser::parse_crt;
+
+    const TREE: &str = "Entities\n\
+        E1. Changes need manual approval {type: root_cause}\n\
+        E2. Tests run only before releases {type: root_cause}\n\
+        E3. Approvals queue up\n\
+        E4. Releases batch up\n\
+        E5. Failures are hard to trace {type: ude}\n\
+        E6. Customers wait for fixes {type: ude}\n\
+        E7. Audits demand sign-off\n\
+        \n\
+        Links\n\
+        L1. E1 → E3 → E4 → E5\n\
+        L2. E4 → E3\n\
+        L3. E2 AND E7 → E6\n\
+        L4. E4 → E6\n\
+        L5. NOT E1 → NOT E7\n";
+
+    #[test]
+    fn neutralises_effects_whose_causes_are_all_lost() {
+        let crt = parse_crt(TREE).unwrap();
+
+        let skeleton = negate_crt(&crt, &[1]).unwrap();
+        assert_eq!(skeleton.neutralised, vec![1, 3, 4, 5]);
+        assert_eq!(skeleton.neutralised_udes, vec![5]);
+        assert_eq!(skeleton.remaining_udes, vec![6]);
+        assert_eq!(check_frt(&skeleton.frt, &crt).uncovered, vec![6]);
+
+        let skeleton = negate_crt(&crt, &[2, 1]).unwrap();
+        assert_eq!(skeleton.neutralised, vec![1, 2, 3, 4, 5, 6]);
+        assert!(skeleton.remaining_udes.is_empty());
+
+        assert!(negate_crt(&crt, &[9]).unwrap_err().to_string().contains("Entity E9 is not in the CRT"));
+    }
+
+    #[test]
+    fn lays_out_negated_chains() {
+        let crt = parse_crt(TREE).unwrap();
+        let skeleton = negate_crt(&crt, &[1, 2]).unwrap();
+        let links: Vec<String> = skeleton.frt.to_string().lines().skip_while(|line| *line != "Links").skip(1).map(String::from).collect();
+        assert_eq!(
+            links,
+            vec![
+                "L1. I1 → NOT E1",
+                "L2. I2 → NOT E2",
+                "L3. NOT E1 AND NOT E4 → NOT E3 → NOT E4",
+                "L4. NOT E4 → NOT E5",
+                "L5. NOT E2 AND NOT E4 → NOT E6",
+            ]
+        );
+        assert_eq!(parse_frt(&skeleton.frt.to_string()).unwrap(), skeleton.frt);
+    }
+}
@@ -1,8 +1,12 @@
 use anyhow::{anyhow, Result};
+use pest::error::LineColLocation;
 use pest::iterators::Pair;
 use pest::Parser;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
@@ -56,13 +60,84 @@ impl Relationship {
 
 #[derive(pest_derive::Parser)]
 #[grammar = "crt.pest"] // put the grammar file at src/crt.pest
-struct CRTParser;
+pub(crate) struct CRTParser;
 
 // ---------- AST ----------
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Entity {
     pub id: u32,
     pub text: String,
+    /// Metrics named in `[metric: ...]` tags after the text, in order.
+    pub metrics: Vec<String>,
+    pub attributes: EntityAttributes,
+}
+
+/// Role of an entity in the tree, set with the `type` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityType {
+    Ude,
+    RootCause,
+    Assumption,
+    Injection,
+    ExternalConstraint,
+}
+
+impl EntityType {
+    pub const ALL: [EntityType; 5] = [
+        EntityType::Ude,
+        EntityType::RootCause,
+        EntityType::Assumption,
+        EntityType::Injection,
+        EntityType::ExternalConstraint,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntityType::Ude => "ude",
+            EntityType::RootCause => "root_cause",
+            EntityType::Assumption => "assumption",
+            EntityType::Injection => "injection",
+            EntityType::ExternalConstraint => "external_constraint",
+        }
+    }
+}
+
+impl FromStr for EntityType {
+    type Err = String;
+
+    /// Case-insensitive, with `-` or a space accepted in place of `_`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase().replace(['-', ' '], "_");
+        EntityType::ALL.into_iter().find(|kind| kind.name() == name).ok_or_else(|| {
+            let names: Vec<&str> = EntityType::ALL.iter().map(EntityType::name).collect();
+            format!("Unknown entity type '{s}' (expected one of {})", names.join(", "))
+        })
+    }
+}
+
+/// Keys accepted in an entity's `{...}` attribute block. A trailing block with
+/// any other key is read as part of the entity's text, like `{env: prod}`.
+pub const ENTITY_ATTRIBUTE_KEYS: &[&str] = &["type", "owner", "confidence", "tags"];
+
+/// Attributes from an entity's `{key: value, ...}` block.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct EntityAttributes {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<EntityType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// How sure the authors are the entity holds, 0–1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl EntityAttributes {
+    pub fn is_empty(&self) -> bool {
+        *self == EntityAttributes::default()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -81,12 +156,39 @@ enum Token {
     RParen,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Link {
     pub id: u32,
     pub segments: Vec<Expr>,
+    /// How firmly the causality is established, 0–1; `None` when not stated.
+    pub confidence: Option<f32>,
+    /// Where the causality was observed, e.g. a report or measurement.
+    pub evidence: Option<String>,
+}
+
+impl Link {
+    /// The stated confidence, or `DEFAULT_LINK_CONFIDENCE`.
+    pub fn confidence_or_default(&self) -> f32 {
+        self.confidence.unwrap_or(DEFAULT_LINK_CONFIDENCE)
+    }
 }
 
+/// The confidence and evidence stated on a link.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct LinkAnnotation {
+    pub confidence: Option<f32>,
+    pub evidence: Option<String>,
+}
+
+/// Keys accepted in a link's `{...}` attribute block.
+pub const LINK_ATTRIBUTE_KEYS: &[&str] = &["confidence", "evidence"];
+
+/// Named confidence levels accepted wherever a 0–1 confidence is.
+pub const CONFIDENCE_LEVELS: &[(&str, f32)] = &[("hypothesis", 0.3), ("likely", 0.6), ("measured", 0.9)];
+
+/// Confidence of links that do not state one: the tree asserts them.
+pub const DEFAULT_LINK_CONFIDENCE: f32 = 1.0;
+
 fn tokenize_expr(input: &str) -> Result<Vec<Token>> {
     let mut tokens = Vec::new();
     let mut chars = input.chars().peekable();
@@ -240,22 +342,91 @@ impl ExprParser {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct CRT {
     pub entities: BTreeMap<u32, Entity>,
     pub links: BTreeMap<u32, Link>,
 }
 
+impl CRT {
+    /// Attributes of every entity that has any, keyed by entity id.
+    pub fn entity_attributes(&self) -> BTreeMap<u32, EntityAttributes> {
+        self.entities
+            .values()
+            .filter(|entity| !entity.attributes.is_empty())
+            .map(|entity| (entity.id, entity.attributes.clone()))
+            .collect()
+    }
+
+    /// Confidence and evidence of every link that states either, keyed by link id.
+    pub fn link_annotations(&self) -> BTreeMap<u32, LinkAnnotation> {
+        self.links
+            .values()
+            .filter(|link| link.confidence.is_some() || link.evidence.is_some())
+            .map(|link| {
+                let annotation = LinkAnnotation {
+                    confidence: link.confidence,
+                    evidence: link.evidence.clone(),
+                };
+                (link.id, annotation)
+            })
+            .collect()
+    }
+}
+
+/// A parse failure located in the source, carried inside the `anyhow::Error`
+/// returned by [`parse_crt`] so callers can downcast and report positions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParseDiagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseDiagnostic {
+    pub(crate) fn at(pair: &Pair<Rule>, message: impl ToString) -> Self {
+        let (line, column) = pair.as_span().start_pos().line_col();
+        ParseDiagnostic {
+            line,
+            column,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseDiagnostic {}
+
 // ---------- API ----------
-pub fn parse_crt(input: &str) -> Result<CRT> {
-    let source: Cow<'_, str> = if input.ends_with('\n') {
+/// `input` with a final newline, which every line rule of the grammar ends with.
+pub(crate) fn line_terminated(input: &str) -> Cow<'_, str> {
+    if input.ends_with('\n') {
         Cow::Borrowed(input)
     } else {
         Cow::Owned(format!("{input}\n"))
+    }
+}
+
+/// Locate a grammar failure in the source.
+pub(crate) fn grammar_diagnostic(e: pest::error::Error<Rule>) -> ParseDiagnostic {
+    let (line, column) = match e.line_col {
+        LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
     };
+    ParseDiagnostic {
+        line,
+        column,
+        message: format!("Parse error: {}", e.variant.message()),
+    }
+}
 
-    let mut pairs =
-        CRTParser::parse(Rule::file, source.as_ref()).map_err(|e| anyhow!("Parse error: {e}"))?;
+pub fn parse_crt(input: &str) -> Result<CRT> {
+    let source = line_terminated(input);
+    let mut pairs = CRTParser::parse(Rule::file, source.as_ref()).map_err(grammar_diagnostic)?;
     let file = pairs.next().unwrap();
 
     let mut entities = BTreeMap::<u32, Entity>::new();
@@ -264,38 +435,49 @@ pub fn parse_crt(input: &str) -> Result<CRT> {
     for section in file.into_inner() {
         match section.as_rule() {
             Rule::entity_line => {
-                let (id, text) = parse_entity_line(section)?;
-                if entities.insert(id, Entity { id, text }).is_some() {
-                    return Err(anyhow!("Duplicate entity E{id}"));
+                let entity = parse_entity_line(section.clone()).map_err(|e| ParseDiagnostic::at(&section, e))?;
+                let id = entity.id;
+                if entities.insert(id, entity).is_some() {
+                    return Err(ParseDiagnostic::at(&section, format!("Duplicate entity E{id}")).into());
                 }
             }
             Rule::link_line => {
-                let link = parse_link_line(section)?;
+                let link = parse_link_line(section.clone()).map_err(|e| ParseDiagnostic::at(&section, e))?;
                 let l = link.clone();
                 if links.insert(l.id, l).is_some() {
-                    return Err(anyhow!("Duplicate link L{}", link.id));
+                    return Err(ParseDiagnostic::at(&section, format!("Duplicate link L{}", link.id)).into());
                 }
+                // entities are declared before links, so references can be checked here
+                validate_refs(&entities, &link).map_err(|e| ParseDiagnostic::at(&section, e))?;
             }
             // headings/blanklines/whitespace are already consumed in the grammar
             _ => {}
         }
     }
 
-    // validate that every referenced entity exists
-    validate_refs(&entities, &links)?;
     Ok(CRT { entities, links })
 }
 
 // ---------- parsers ----------
-fn parse_entity_line(p: Pair<Rule>) -> Result<(u32, String)> {
-    // entity_line = { ws* "E" ID "." ws* text eol }
+fn parse_entity_line(p: Pair<Rule>) -> Result<Entity> {
+    // entity_line = { ws* "E" ID "." ws* text (metric_tag | entity_attributes)* eol }
     let mut id: Option<u32> = None;
     let mut label: Option<String> = None;
+    let mut metrics = Vec::new();
+    let mut attributes = Vec::new();
 
     for part in p.into_inner() {
         match part.as_rule() {
             Rule::ID => id = Some(part.as_str().parse()?),
             Rule::text => label = Some(part.as_str().trim().to_string()),
+            Rule::metric_tag => {
+                for name in part.into_inner().filter(|name| name.as_rule() == Rule::metric_name) {
+                    if !metrics.iter().any(|metric| metric == name.as_str()) {
+                        metrics.push(name.as_str().to_string());
+                    }
+                }
+            }
+            Rule::entity_attributes => attribute_pairs(part, &mut attributes)?,
             _ => {}
         }
     }
@@ -304,18 +486,179 @@ fn parse_entity_line(p: Pair<Rule>) -> Result<(u32, String)> {
     if text.is_empty() {
         return Err(anyhow!("Entity E{id} has empty text"));
     }
-    Ok((id, text))
+    let mut entity = Entity {
+        id,
+        text,
+        metrics,
+        attributes: EntityAttributes::default(),
+    };
+    for (key, value) in attributes {
+        apply_entity_attribute(&key, value, &mut entity.attributes)?;
+    }
+    Ok(entity)
+}
+
+/// Split an attribute block into its key/value pairs, rejecting repeated keys.
+pub(crate) fn attribute_pairs<'a>(block: Pair<'a, Rule>, pairs: &mut Vec<(String, Pair<'a, Rule>)>) -> Result<()> {
+    // attribute = { attr_key ":" (attr_list | attr_string | attr_bare) }
+    for attribute in block.into_inner() {
+        let mut parts = attribute.into_inner();
+        let key = parts.next().ok_or_else(|| anyhow!("Missing attribute key"))?.as_str().to_string();
+        let value = parts.next().ok_or_else(|| anyhow!("Missing value for attribute '{key}'"))?;
+        if pairs.iter().any(|(seen, _)| *seen == key) {
+            return Err(anyhow!("Duplicate attribute '{key}'"));
+        }
+        pairs.push((key, value));
+    }
+    Ok(())
+}
+
+pub(crate) fn unknown_attribute(key: &str, expected: &[&str]) -> anyhow::Error {
+    anyhow!("Unknown {} (expected {})", key, expected.join(", "))
+}
+
+fn apply_entity_attribute(key: &str, value: Pair<Rule>, attributes: &mut EntityAttributes) -> Result<()> {
+    match key {
+        "type" => attributes.kind = Some(attribute_scalar(key, value)?.parse().map_err(|e: String| anyhow!(e))?),
+        "owner" => attributes.owner = Some(attribute_scalar(key, value)?),
+        "confidence" => attributes.confidence = Some(attribute_confidence(key, value)?),
+        "tags" => attributes.tags = attribute_values(key, value)?,
+        _ => return Err(unknown_attribute(&format!("entity attribute '{key}'"), ENTITY_ATTRIBUTE_KEYS)),
+    }
+    Ok(())
+}
+
+pub(crate) fn apply_link_attribute(key: &str, value: Pair<Rule>, annotation: &mut LinkAnnotation) -> Result<()> {
+    match key {
+        "confidence" => annotation.confidence = Some(attribute_confidence(key, value)?),
+        "evidence" => annotation.evidence = Some(attribute_scalar(key, value)?),
+        _ => return Err(unknown_attribute(&format!("link attribute '{key}'"), LINK_ATTRIBUTE_KEYS)),
+    }
+    Ok(())
+}
+
+/// A confidence given as a number from 0 to 1 or as one of `CONFIDENCE_LEVELS`.
+fn attribute_confidence(key: &str, value: Pair<Rule>) -> Result<f32> {
+    let text = attribute_scalar(key, value)?;
+    if let Some((_, level)) = CONFIDENCE_LEVELS.iter().find(|(name, _)| text.eq_ignore_ascii_case(name)) {
+        return Ok(*level);
+    }
+    let levels: Vec<&str> = CONFIDENCE_LEVELS.iter().map(|(name, _)| *name).collect();
+    let confidence: f32 = text
+        .parse()
+        .map_err(|_| anyhow!("Confidence '{text}' must be a number or one of {}", levels.join(", ")))?;
+    if !(0.0..=1.0).contains(&confidence) {
+        return Err(anyhow!("Confidence must be between 0 and 1 (got {confidence})"));
+    }
+    Ok(confidence)
+}
+
+/// The items of a list value, or a scalar value as a single item.
+pub(crate) fn attribute_values(key: &str, value: Pair<Rule>) -> Result<Vec<String>> {
+    match value.as_rule() {
+        Rule::attr_list => Ok(value
+            .into_inner()
+            .map(|item| item.into_inner().next().map(|inner| unquote(inner.as_str())).unwrap_or_default())
+            .filter(|item| !item.is_empty())
+            .collect()),
+        _ => Ok(vec![attribute_scalar(key, value)?]),
+    }
+}
+
+/// Ids of an attribute listing references like `[E12, E18]`, all with `prefix`.
+pub(crate) fn attribute_refs(key: &str, value: Pair<Rule>, prefix: &str) -> Result<Vec<u32>> {
+    attribute_values(key, value)?
+        .iter()
+        .map(|item| {
+            item.strip_prefix(prefix)
+                .and_then(|digits| digits.parse().ok())
+                .ok_or_else(|| anyhow!("Attribute '{key}' takes references like {prefix}12 (got '{item}')"))
+        })
+        .collect()
+}
+
+/// A single attribute value, with quotes removed and surrounding space trimmed.
+pub(crate) fn attribute_scalar(key: &str, value: Pair<Rule>) -> Result<String> {
+    if value.as_rule() == Rule::attr_list {
+        return Err(anyhow!("Attribute '{key}' takes a single value"));
+    }
+    let text = unquote(value.as_str());
+    if text.is_empty() {
+        return Err(anyhow!("Attribute '{key}' must not be empty"));
+    }
+    Ok(text)
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|inner| inner.strip_suffix('"'))
+        .unwrap_or(value)
+        .trim()
+        .to_string()
+}
+
+/// `text` as it can be written into a TOC document, as a statement or a
+/// quoted attribute value, and read back unchanged: runs of whitespace,
+/// newlines included, become one space, slashes are kept apart so none
+/// starts a `//` comment, braces become parentheses so none opens an
+/// attribute block, and double quotes become single ones.
+pub(crate) fn document_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for word in text.split_whitespace() {
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        for c in word.chars() {
+            match c {
+                '/' if out.ends_with('/') => out.push_str(" /"),
+                '{' => out.push('('),
+                '}' => out.push(')'),
+                '"' => out.push('\''),
+                c => out.push(c),
+            }
+        }
+    }
+    out
+}
+
+/// A numbered line of a TOC document, e.g. an FRT injection or a PRT
+/// obstacle, before its attributes are applied.
+pub(crate) struct Statement<'a> {
+    pub id: u32,
+    pub text: String,
+    pub attributes: Vec<(String, Pair<'a, Rule>)>,
+}
+
+pub(crate) fn parse_statement_line(p: Pair<Rule>) -> Result<Statement> {
+    // e.g. injection_line = { ws* "I" ID "." ws* statement attributes? eol }
+    let mut id = None;
+    let mut text = String::new();
+    let mut attributes = Vec::new();
+    for part in p.into_inner() {
+        match part.as_rule() {
+            Rule::ID => id = Some(part.as_str().parse()?),
+            Rule::statement => text = part.as_str().trim().to_string(),
+            Rule::attributes => attribute_pairs(part, &mut attributes)?,
+            _ => {}
+        }
+    }
+    let id = id.ok_or_else(|| anyhow!("Missing ID"))?;
+    Ok(Statement { id, text, attributes })
 }
 
 fn parse_link_line(p: Pair<Rule>) -> Result<Link> {
-    // link_line = { ws* "L" ID "." ws* expr ws* ARROW ws* expr eol }
+    // link_line = { ws* "L" ID "." ws* expr ws* ARROW ws* expr attributes? eol }
     let mut id: Option<u32> = None;
     let mut exprs: Vec<Pair<Rule>> = Vec::new();
+    let mut attributes = Vec::new();
 
     for part in p.into_inner() {
         match part.as_rule() {
             Rule::ID => id = Some(part.as_str().parse()?),
             Rule::expr => exprs.push(part),
+            Rule::attributes => attribute_pairs(part, &mut attributes)?,
             _ => {}
         }
     }
@@ -330,7 +673,16 @@ fn parse_link_line(p: Pair<Rule>) -> Result<Link> {
     for expr_pair in exprs {
         segments.push(parse_expr(expr_pair)?);
     }
-    Ok(Link { id, segments })
+    let mut annotation = LinkAnnotation::default();
+    for (key, value) in attributes {
+        apply_link_attribute(&key, value, &mut annotation)?;
+    }
+    Ok(Link {
+        id,
+        segments,
+        confidence: annotation.confidence,
+        evidence: annotation.evidence,
+    })
 }
 
 fn parse_expr(p: Pair<Rule>) -> Result<Expr> {
@@ -348,7 +700,7 @@ fn parse_expr(p: Pair<Rule>) -> Result<Expr> {
     ExprParser::new(tokens).parse()
 }
 
-fn validate_refs(entities: &BTreeMap<u32, Entity>, links: &BTreeMap<u32, Link>) -> Result<()> {
+fn validate_refs(entities: &BTreeMap<u32, Entity>, link: &Link) -> Result<()> {
     fn collect(expr: &Expr, out: &mut Vec<u32>) {
         match expr {
             Expr::EntityRef(id) => out.push(*id),
@@ -356,19 +708,17 @@ fn validate_refs(entities: &BTreeMap<u32, Entity>, links: &BTreeMap<u32, Link>)
             Expr::And(items) => items.iter().for_each(|e| collect(e, out)),
         }
     }
-    for link in links.values() {
-        let mut ids = Vec::new();
-        for expr in &link.segments {
-            collect(expr, &mut ids);
-        }
-        for id in ids {
-            if !entities.contains_key(&id) {
-                return Err(anyhow!(
-                    "Link L{} references undefined entity E{}",
-                    link.id,
-                    id
-                ));
-            }
+    let mut ids = Vec::new();
+    for expr in &link.segments {
+        collect(expr, &mut ids);
+    }
+    for id in ids {
+        if !entities.contains_key(&id) {
+            return Err(anyhow!(
+                "Link L{} references undefined entity E{}",
+                link.id,
+                id
+            ));
         }
     }
     Ok(())
@@ -406,6 +756,90 @@ L1. (E1 AND E2) → E3
         parse_crt(data).expect("fixture CRT.neo should parse");
     }
 
+    #[test]
+    fn parses_metric_tags() {
+        let input = "Entities\nE1. Delivery is unpredictable [metric: lead_time, mttr] [Metric:lead_time]\nE2. Plain\n\nLinks\nL1. E1 → E2\n";
+        let crt = parse_crt(input).expect("tagged entity should parse");
+        assert_eq!(crt.entities[&1].text, "Delivery is unpredictable");
+        assert_eq!(crt.entities[&1].metrics, vec!["lead_time", "mttr"]);
+        assert!(crt.entities[&2].metrics.is_empty());
+    }
+
+    #[test]
+    fn parses_entity_attributes() {
+        let input = "Entities\n\
+                     E1. Rework {type: root-cause, owner: \"team a, platform\"} [metric: lead_time]\n\
+                     E2. Customers churn {type: UDE, confidence: 0.7, tags: [revenue, \"support load\"]}\n\
+                     E3. Plain {not an attribute block}\n\
+                     E4. Config {env: prod}\n\
+                     E5. Set {type: ude} mid-sentence {owner: ops, colour: red}\n\
+                     \n\
+                     Links\n\
+                     L1. E1 → E2\n";
+        let crt = parse_crt(input).expect("attributes should parse");
+        let rework = &crt.entities[&1];
+        assert_eq!(rework.text, "Rework");
+        assert_eq!(rework.attributes.kind, Some(EntityType::RootCause));
+        assert_eq!(rework.attributes.owner.as_deref(), Some("team a, platform"));
+        assert_eq!(rework.metrics, vec!["lead_time"]);
+
+        let churn = &crt.entities[&2].attributes;
+        assert_eq!(churn.kind, Some(EntityType::Ude));
+        assert_eq!(churn.confidence, Some(0.7));
+        assert_eq!(churn.tags, vec!["revenue", "support load"]);
+
+        assert_eq!(crt.entities[&3].text, "Plain {not an attribute block}");
+        assert_eq!(crt.entities[&4].text, "Config {env: prod}");
+        assert_eq!(crt.entities[&5].text, "Set {type: ude} mid-sentence {owner: ops, colour: red}");
+        assert_eq!(crt.entities[&5].attributes, EntityAttributes::default());
+        assert_eq!(crt.entity_attributes().keys().copied().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn rejects_invalid_entity_attributes() {
+        let message = |attributes: &str| {
+            let input = format!("Entities\nE1. First {attributes}\n\nLinks\n");
+            let err = parse_crt(&input).unwrap_err();
+            err.downcast_ref::<ParseDiagnostic>().expect("diagnostic").message.clone()
+        };
+        assert!(message("{type: symptom}").starts_with("Unknown entity type 'symptom'"));
+        assert_eq!(message("{confidence: 1.5}"), "Confidence must be between 0 and 1 (got 1.5)");
+        assert_eq!(message("{owner: a, owner: b}"), "Duplicate attribute 'owner'");
+
+        // the grammar's entity_key must name the same keys
+        for key in ENTITY_ATTRIBUTE_KEYS {
+            let input = format!("Entities\nE1. First {{{key}: x}}\n\nLinks\n");
+            assert!(parse_crt(&input).map_or(true, |crt| crt.entities[&1].text == "First"), "{key}");
+        }
+    }
+
+    #[test]
+    fn parses_link_attributes() {
+        let input = "Entities\nE1. First\nE2. Second\nE3. Third\n\nLinks\n\
+                     L1. E1 → E2 {confidence: hypothesis}\n\
+                     L2. E2 -> E3 {confidence: 0.8, evidence: \"incident review, March\"} // measured\n\
+                     L3. E1 → E3\n";
+        let crt = parse_crt(input).expect("link attributes should parse");
+        assert_eq!(crt.links[&1].confidence, Some(0.3));
+        assert_eq!(crt.links[&2].confidence, Some(0.8));
+        assert_eq!(crt.links[&2].evidence.as_deref(), Some("incident review, March"));
+        assert_eq!(crt.links[&2].segments[1], Expr::EntityRef(3));
+        assert_eq!(crt.links[&3].confidence_or_default(), DEFAULT_LINK_CONFIDENCE);
+
+        let err = parse_crt("Entities\nE1. A\nE2. B\n\nLinks\nL1. E1 → E2 {owner: me}\n").unwrap_err();
+        let diagnostic = err.downcast_ref::<ParseDiagnostic>().expect("diagnostic");
+        assert_eq!(diagnostic.message, "Unknown link attribute 'owner' (expected confidence, evidence)");
+    }
+
+    #[test]
+    fn reports_position_of_undefined_reference() {
+        let input = "Entities\nE1. First\n\nLinks\nL1. E1 → E7\n";
+        let err = parse_crt(input).unwrap_err();
+        let diagnostic = err.downcast_ref::<ParseDiagnostic>().expect("diagnostic");
+        assert_eq!(diagnostic.line, 5);
+        assert!(diagnostic.message.contains("undefined entity E7"));
+    }
+
     #[test]
     fn raw_expr_parses() {
         let result = parse_expr(
@@ -0,0 +1,326 @@
+//! Path queries: how one set of entities leads to another, for answering
+//! "how does E1 lead to E22?" and tracing it in the visualisation.
+
+use crate::graph::{CausalGraph, CausalPath, Edge};
+use crate::parser::CRT;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// Bounds on the search for simple paths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PathLimits {
+    pub max_paths: usize,
+    /// Longer paths are left out.
+    pub max_steps: usize,
+    /// Steps followed, over every pass of the search, before it stops.
+    pub max_visits: usize,
+}
+
+impl Default for PathLimits {
+    fn default() -> Self {
+        PathLimits {
+            max_paths: 50,
+            max_steps: 12,
+            max_visits: 100_000,
+        }
+    }
+}
+
+/// A path with what else it needs to carry.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TracedPath {
+    #[serde(flatten)]
+    pub path: CausalPath,
+    /// Other inputs of the `AND` junctions the path runs through, which must
+    /// hold too; empty when the path carries on its own. Entities on the path
+    /// or among the starting entities are left out.
+    pub requires: Vec<u32>,
+}
+
+/// One step of a link between two entities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct PathStep {
+    pub from: u32,
+    pub to: u32,
+    pub link: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct PathQuery {
+    /// Simple paths, fewest steps first, each ending at the first target it
+    /// reaches. When the search is cut short, every path shorter than the
+    /// longest kept is there, and only some of that length.
+    pub paths: Vec<TracedPath>,
+    /// Whether `max_paths` or `max_visits` cut the search short.
+    pub truncated: bool,
+    /// The path with the fewest steps, found whatever the limits.
+    pub shortest: Option<TracedPath>,
+    /// Every step on some chain from the starting entities to a target.
+    pub steps: Vec<PathStep>,
+    /// The links of `steps`.
+    pub links: Vec<u32>,
+}
+
+/// Trace how any of `from` leads to any of `to`.
+pub fn query_paths(crt: &CRT, from: &[u32], to: &[u32], limits: PathLimits) -> Result<PathQuery> {
+    if from.is_empty() || to.is_empty() {
+        return Err(anyhow!("Choose entities to trace from and to"));
+    }
+    if let Some(id) = from.iter().chain(to).find(|id| !crt.entities.contains_key(id)) {
+        return Err(anyhow!("Entity E{id} is not in the CRT"));
+    }
+    let graph = CausalGraph::from_crt(crt);
+    let sources: BTreeSet<u32> = from.iter().copied().collect();
+    let targets: BTreeSet<u32> = to.iter().copied().collect();
+
+    // one pass per length, so paths are found shortest first
+    let mut search = Search {
+        graph: &graph,
+        targets: &targets,
+        limits,
+        found: Vec::new(),
+        visits: 0,
+        deeper: false,
+        truncated: false,
+    };
+    for depth in 1..=limits.max_steps {
+        search.deeper = false;
+        for &source in &sources {
+            search.walk(source, depth, &mut Vec::new(), &mut BTreeSet::from([source]));
+        }
+        if search.truncated || !search.deeper {
+            break;
+        }
+    }
+    let truncated = search.truncated;
+    let mut paths: Vec<TracedPath> = search.found.iter().map(|edges| traced(&graph, edges, &sources)).collect();
+    paths.sort_by(|a, b| {
+        (a.path.links.len(), &a.path.entities, &a.path.links).cmp(&(b.path.links.len(), &b.path.entities, &b.path.links))
+    });
+
+    // a step is on a chain when its start is reached from a source without
+    // passing a target, and a target is reached from its end
+    let forward = closure(&sources, |id| match targets.contains(&id) {
+        true => BTreeSet::new(),
+        false => graph.neighbours_out(id),
+    });
+    let backward = closure(&targets, |id| graph.neighbours_in(id));
+    let steps: BTreeSet<PathStep> = graph
+        .edges
+        .iter()
+        .filter(|edge| forward.contains(&edge.from) && !targets.contains(&edge.from) && backward.contains(&edge.to))
+        .map(|edge| PathStep {
+            from: edge.from,
+            to: edge.to,
+            link: edge.link,
+        })
+        .collect();
+
+    Ok(PathQuery {
+        paths,
+        truncated,
+        shortest: shortest(&graph, &sources, &targets).map(|edges| traced(&graph, &edges, &sources)),
+        links: steps.iter().map(|step| step.link).collect::<BTreeSet<_>>().into_iter().collect(),
+        steps: steps.into_iter().collect(),
+    })
+}
+
+/// A depth-limited search for simple paths, repeated with a growing depth.
+struct Search<'g> {
+    graph: &'g CausalGraph,
+    targets: &'g BTreeSet<u32>,
+    limits: PathLimits,
+    found: Vec<Vec<&'g Edge>>,
+    visits: usize,
+    /// Whether a path of the current depth could carry on.
+    deeper: bool,
+    truncated: bool,
+}
+
+impl<'g> Search<'g> {
+    /// Depth-first search for simple paths from `node` that reach a target in
+    /// exactly `depth` more steps. Stops once `max_visits` steps are followed,
+    /// or `max_paths` are found and another turns up.
+    fn walk(&mut self, node: u32, depth: usize, path: &mut Vec<&'g Edge>, visited: &mut BTreeSet<u32>) {
+        let graph = self.graph;
+        for edge in graph.successors(node) {
+            if self.truncated {
+                return;
+            }
+            if visited.contains(&edge.to) {
+                continue;
+            }
+            if self.visits == self.limits.max_visits {
+                self.truncated = true;
+                return;
+            }
+            self.visits += 1;
+            path.push(edge);
+            if self.targets.contains(&edge.to) {
+                if depth == 1 && self.found.len() == self.limits.max_paths {
+                    self.truncated = true;
+                } else if depth == 1 {
+                    self.found.push(path.clone());
+                }
+            } else if depth == 1 {
+                self.deeper = true;
+            } else {
+                visited.insert(edge.to);
+                self.walk(edge.to, depth - 1, path, visited);
+                visited.remove(&edge.to);
+            }
+            path.pop();
+        }
+    }
+}
+
+/// Breadth-first search from all of `sources` to the nearest target.
+fn shortest<'g>(graph: &'g CausalGraph, sources: &BTreeSet<u32>, targets: &BTreeSet<u32>) -> Option<Vec<&'g Edge>> {
+    let mut previous: BTreeMap<u32, &Edge> = BTreeMap::new();
+    let mut seen = sources.clone();
+    let mut queue: VecDeque<u32> = sources.iter().copied().collect();
+    while let Some(node) = queue.pop_front() {
+        if targets.contains(&node) && previous.contains_key(&node) {
+            let mut edges = Vec::new();
+            let mut at = node;
+            // sources are seen from the start, so they never gain a predecessor
+            while let Some(edge) = previous.get(&at) {
+                edges.push(*edge);
+                at = edge.from;
+            }
+            edges.reverse();
+            return Some(edges);
+        }
+        for edge in graph.successors(node) {
+            if seen.insert(edge.to) {
+                previous.insert(edge.to, edge);
+                queue.push_back(edge.to);
+            }
+        }
+    }
+    None
+}
+
+fn traced(graph: &CausalGraph, edges: &[&Edge], sources: &BTreeSet<u32>) -> TracedPath {
+    let mut entities = vec![edges[0].from];
+    entities.extend(edges.iter().map(|edge| edge.to));
+    let requires: BTreeSet<u32> = edges
+        .iter()
+        .flat_map(|edge| {
+            graph
+                .predecessors(edge.to)
+                .filter(|other| other.link == edge.link && other.segment == edge.segment)
+                .map(|other| other.from)
+        })
+        .filter(|id| !entities.contains(id) && !sources.contains(id))
+        .collect();
+    TracedPath {
+        path: CausalPath {
+            links: edges.iter().map(|edge| edge.link).collect(),
+            confidence: edges.iter().map(|edge| edge.confidence).product(),
+            entities,
+        },
+        requires: requires.into_iter().collect(),
+    }
+}
+
+/// `start` and everything reachable from it through `next`.
+fn closure(start: &BTreeSet<u32>, next: impl Fn(u32) -> BTreeSet<u32>) -> BTreeSet<u32> {
+    let mut reached = start.clone();
+    let mut stack: Vec<u32> = start.iter().copied().collect();
+    while let Some(id) = stack.pop() {
+        for id in next(id) {
+            if reached.insert(id) {
+                stack.push(id);
+            }
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_crt;
+
+    const TREE: &str = "Entities\n\
+        E1. Root\n\
+        E2. Firm step\n\
+        E3. Hypothetical step\n\
+        E4. Effect\n\
+        E5. Other root\n\
+        E6. Later effect\n\
+        \n\
+        Links\n\
+        L1. E1 → E2 {confidence: 0.9}\n\
+        L2. E2 → E4 → E6 {confidence: 0.8}\n\
+        L3. E1 → E3\n\
+        L4. E3 → E4 {confidence: hypothesis}\n\
+        L5. E5 AND E3 → E4 {confidence: 0.1}\n";
+
+    #[test]
+    fn traces_paths_through_and_junctions() {
+        let crt = parse_crt(TREE).unwrap();
+        let query = query_paths(&crt, &[1], &[4], PathLimits::default()).unwrap();
+
+        let traced: Vec<(Vec<u32>, Vec<u32>, Vec<u32>)> = query
+            .paths
+            .iter()
+            .map(|path| (path.path.entities.clone(), path.path.links.clone(), path.requires.clone()))
+            .collect();
+        assert_eq!(
+            traced,
+            vec![
+                (vec![1, 2, 4], vec![1, 2], vec![]),
+                (vec![1, 3, 4], vec![3, 4], vec![]),
+                (vec![1, 3, 4], vec![3, 5], vec![5]),
+            ]
+        );
+        assert!(!query.truncated);
+        assert_eq!(query.shortest.unwrap().path.links, vec![1, 2]);
+        // L2 continues past E4 to E6, and L5's step from E5 starts outside the trace
+        assert_eq!(query.links, vec![1, 2, 3, 4, 5]);
+        assert!(!query.steps.contains(&PathStep { from: 4, to: 6, link: 2 }));
+        assert!(!query.steps.contains(&PathStep { from: 5, to: 4, link: 5 }));
+
+        // starting from both inputs of the AND junction satisfies it
+        let query = query_paths(&crt, &[5, 3], &[6], PathLimits::default()).unwrap();
+        assert!(query.paths.iter().all(|path| path.requires.is_empty()));
+    }
+
+    #[test]
+    fn bounds_the_search() {
+        let crt = parse_crt(TREE).unwrap();
+        let query = query_paths(&crt, &[1], &[4], PathLimits { max_paths: 2, ..PathLimits::default() }).unwrap();
+        assert_eq!((query.paths.len(), query.truncated), (2, true));
+
+        let query = query_paths(&crt, &[1], &[6], PathLimits { max_steps: 2, ..PathLimits::default() }).unwrap();
+        assert!(query.paths.is_empty());
+        assert_eq!(query.shortest.unwrap().path.entities, vec![1, 2, 4, 6]);
+
+        assert!(query_paths(&crt, &[6], &[1], PathLimits::default()).unwrap().shortest.is_none());
+        let error = query_paths(&crt, &[1], &[9], PathLimits::default()).unwrap_err();
+        assert!(error.to_string().contains("Entity E9 is not in the CRT"));
+    }
+
+    #[test]
+    fn keeps_the_shortest_paths_when_cut_short() {
+        let crt = parse_crt("Entities\nE1. A\nE2. B\nE3. C\nE4. D\n\nLinks\nL1. E1 → E2 → E3 → E4\nL2. E1 → E4\n").unwrap();
+        let entities = |query: &PathQuery| -> Vec<Vec<u32>> { query.paths.iter().map(|path| path.path.entities.clone()).collect() };
+
+        let query = query_paths(&crt, &[1], &[4], PathLimits::default()).unwrap();
+        assert_eq!(entities(&query), vec![vec![1, 4], vec![1, 2, 3, 4]]);
+
+        // a depth-first walk would find the longer path through L1 first
+        let query = query_paths(&crt, &[1], &[4], PathLimits { max_paths: 1, ..PathLimits::default() }).unwrap();
+        assert_eq!((entities(&query), query.truncated), (vec![vec![1, 4]], true));
+
+        // the first pass follows E1 → E2 and E1 → E4, and the second runs out
+        let query = query_paths(&crt, &[1], &[4], PathLimits { max_visits: 2, ..PathLimits::default() }).unwrap();
+        assert_eq!((entities(&query), query.truncated), (vec![vec![1, 4]], true));
+        let query = query_paths(&crt, &[1], &[4], PathLimits { max_visits: 1, ..PathLimits::default() }).unwrap();
+        assert!(query.paths.is_empty() && query.truncated);
+        assert_eq!(query.shortest.unwrap().path.entities, vec![1, 4]);
+    }
+}
@@ -0,0 +1,359 @@
+//! Prerequisite Trees: the obstacles between a team and its goal, and the
+//! intermediate objectives that overcome them, ordered by what each requires.
+
+use crate::parser::{
+    attribute_refs, document_text, grammar_diagnostic, line_terminated, parse_statement_line, unknown_attribute, CRTParser,
+    ParseDiagnostic, Rule, Statement,
+};
+use crate::types::PlannerOutput;
+use anyhow::{anyhow, Result};
+use pest::iterators::Pair;
+use pest::Parser;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Obstacle {
+    pub id: u32,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct IntermediateObjective {
+    pub id: u32,
+    pub text: String,
+    pub overcomes: Vec<u32>,
+    /// Objectives that must be reached first.
+    pub requires: Vec<u32>,
+}
+
+/// Keys accepted in an objective's `{...}` block. Obstacles take none.
+pub const OBJECTIVE_ATTRIBUTE_KEYS: &[&str] = &["overcomes", "requires"];
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct PrerequisiteTree {
+    pub goal: String,
+    pub obstacles: BTreeMap<u32, Obstacle>,
+    pub objectives: BTreeMap<u32, IntermediateObjective>,
+}
+
+impl PrerequisiteTree {
+    /// Obstacles no objective overcomes yet.
+    pub fn open_obstacles(&self) -> Vec<u32> {
+        let overcome: BTreeSet<u32> = self.objectives.values().flat_map(|o| o.overcomes.iter().copied()).collect();
+        self.obstacles.keys().copied().filter(|id| !overcome.contains(id)).collect()
+    }
+
+    /// Objectives in an order that reaches each after those it requires, the
+    /// lowest id first among those ready. Fails with the objectives left over
+    /// when `requires` forms a cycle.
+    pub fn order(&self) -> Result<Vec<u32>, Vec<u32>> {
+        let mut done = BTreeSet::new();
+        let mut order = Vec::with_capacity(self.objectives.len());
+        while order.len() < self.objectives.len() {
+            let ready = self
+                .objectives
+                .values()
+                .find(|o| !done.contains(&o.id) && o.requires.iter().all(|r| done.contains(r)));
+            match ready {
+                Some(objective) => {
+                    done.insert(objective.id);
+                    order.push(objective.id);
+                }
+                None => return Err(self.objectives.keys().copied().filter(|id| !done.contains(id)).collect()),
+            }
+        }
+        Ok(order)
+    }
+
+    /// A draft from a planner agent's 90-day plan. Interventions and risk
+    /// mitigations become objectives overcoming the constraint or risk they
+    /// address; stated phase objectives become objectives of their own. Every
+    /// objective requires the previous phase's stated objectives (or all its
+    /// objectives when it states none). Text is made safe to export with
+    /// `document_text`, and fails when nothing is left of it.
+    pub fn from_plan(plan: &PlannerOutput) -> Result<Self> {
+        let summary = plan.executive_summary.clone().unwrap_or_default();
+        let mut tree = PrerequisiteTree {
+            goal: [summary.expected_outcome, summary.intervention_strategy, summary.primary_constraint]
+                .iter()
+                .flatten()
+                .map(|text| document_text(text))
+                .find(|text| !text.is_empty())
+                .unwrap_or_else(|| "Reach the plan's expected outcome".to_string()),
+            ..Default::default()
+        };
+
+        let mut previous: Vec<u32> = Vec::new();
+        for (name, phase) in plan.phases() {
+            let text = |text: &str, what: &str| -> Result<String> {
+                let text = document_text(text);
+                if text.is_empty() {
+                    return Err(anyhow!("Phase '{name}' has an empty {what}"));
+                }
+                Ok(text)
+            };
+            let mut stated = Vec::new();
+            let mut all = Vec::new();
+            for objective in &phase.objectives {
+                let id = tree.add_objective(text(objective, "objective")?, Vec::new(), &previous);
+                stated.push(id);
+                all.push(id);
+            }
+            for intervention in &phase.interventions {
+                let mut overcomes = Vec::new();
+                if let Some(constraint) = &intervention.constraint_addressed {
+                    overcomes.push(tree.add_obstacle(text(constraint, "constraint")?));
+                }
+                all.push(tree.add_objective(text(&intervention.name, "intervention name")?, overcomes, &previous));
+            }
+            for risk in &phase.risks {
+                let obstacle = tree.add_obstacle(text(&risk.risk, "risk")?);
+                if let Some(mitigation) = &risk.mitigation {
+                    all.push(tree.add_objective(text(mitigation, "mitigation")?, vec![obstacle], &previous));
+                }
+            }
+            if !all.is_empty() {
+                previous = if stated.is_empty() { all } else { stated };
+            }
+        }
+        Ok(tree)
+    }
+
+    /// The id of the obstacle stating `text`, added when new.
+    fn add_obstacle(&mut self, text: String) -> u32 {
+        if let Some(obstacle) = self.obstacles.values().find(|o| o.text == text) {
+            return obstacle.id;
+        }
+        let id = self.obstacles.keys().last().map_or(1, |last| last + 1);
+        self.obstacles.insert(id, Obstacle { id, text });
+        id
+    }
+
+    fn add_objective(&mut self, text: String, overcomes: Vec<u32>, requires: &[u32]) -> u32 {
+        let id = self.objectives.keys().last().map_or(1, |last| last + 1);
+        let objective = IntermediateObjective {
+            id,
+            text,
+            overcomes,
+            requires: requires.to_vec(),
+        };
+        self.objectives.insert(id, objective);
+        id
+    }
+}
+
+impl fmt::Display for PrerequisiteTree {
+    /// The tree in the grammar `parse_prt` reads.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let refs = |prefix: &str, ids: &[u32]| -> String {
+            let refs: Vec<String> = ids.iter().map(|id| format!("{prefix}{id}")).collect();
+            format!("[{}]", refs.join(", "))
+        };
+
+        writeln!(f, "Goal\n{}\n\nObstacles", document_text(&self.goal))?;
+        for obstacle in self.obstacles.values() {
+            writeln!(f, "O{}. {}", obstacle.id, document_text(&obstacle.text))?;
+        }
+        writeln!(f, "\nObjectives")?;
+        for objective in self.objectives.values() {
+            write!(f, "IO{}. {}", objective.id, document_text(&objective.text))?;
+            let mut attributes = Vec::new();
+            if !objective.overcomes.is_empty() {
+                attributes.push(format!("overcomes: {}", refs("O", &objective.overcomes)));
+            }
+            if !objective.requires.is_empty() {
+                attributes.push(format!("requires: {}", refs("IO", &objective.requires)));
+            }
+            if !attributes.is_empty() {
+                write!(f, " {{{}}}", attributes.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// ---------- API ----------
+pub fn parse_prt(input: &str) -> Result<PrerequisiteTree> {
+    let source = line_terminated(input);
+    let mut pairs = CRTParser::parse(Rule::prt_file, source.as_ref()).map_err(grammar_diagnostic)?;
+    let file = pairs.next().unwrap();
+
+    let mut tree = PrerequisiteTree::default();
+    for line in file.into_inner() {
+        let at = |e: anyhow::Error| ParseDiagnostic::at(&line, e);
+        match line.as_rule() {
+            Rule::heading_text => tree.goal = line.as_str().trim().to_string(),
+            Rule::obstacle_line => {
+                let Statement { id, text, attributes } = parse_statement_line(line.clone()).map_err(at)?;
+                if let Some((key, _)) = attributes.first() {
+                    return Err(at(anyhow!("Obstacles take no attributes (got '{key}')")).into());
+                }
+                if tree.obstacles.insert(id, Obstacle { id, text }).is_some() {
+                    return Err(at(anyhow!("Duplicate obstacle O{id}")).into());
+                }
+            }
+            Rule::objective_line => {
+                let objective = parse_objective_line(line.clone()).map_err(at)?;
+                if let Some(id) = objective.overcomes.iter().find(|id| !tree.obstacles.contains_key(id)) {
+                    return Err(at(anyhow!("Objective IO{} overcomes undefined obstacle O{id}", objective.id)).into());
+                }
+                let id = objective.id;
+                if tree.objectives.insert(id, objective).is_some() {
+                    return Err(at(anyhow!("Duplicate objective IO{id}")).into());
+                }
+            }
+            // headings/blanklines/whitespace are already consumed in the grammar
+            _ => {}
+        }
+    }
+
+    // objectives may require ones listed after them, so these are checked last
+    for objective in tree.objectives.values() {
+        if let Some(id) = objective.requires.iter().find(|id| !tree.objectives.contains_key(id)) {
+            return Err(anyhow!("Objective IO{} requires undefined objective IO{id}", objective.id));
+        }
+    }
+    if let Err(cycle) = tree.order() {
+        let ids: Vec<String> = cycle.iter().map(|id| format!("IO{id}")).collect();
+        return Err(anyhow!("Objectives {} require each other in a cycle", ids.join(", ")));
+    }
+    Ok(tree)
+}
+
+// ---------- parsers ----------
+fn parse_objective_line(p: Pair<Rule>) -> Result<IntermediateObjective> {
+    let Statement { id, text, attributes } = parse_statement_line(p)?;
+    let mut objective = IntermediateObjective {
+        id,
+        text,
+        overcomes: Vec::new(),
+        requires: Vec::new(),
+    };
+    for (key, value) in attributes {
+        match key.as_str() {
+            "overcomes" => objective.overcomes = attribute_refs(&key, value, "O")?,
+            "requires" => objective.requires = attribute_refs(&key, value, "IO")?,
+            _ => return Err(unknown_attribute(&format!("objective attribute '{key}'"), OBJECTIVE_ATTRIBUTE_KEYS)),
+        }
+    }
+    Ok(objective)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRT: &str = "Goal\n\
+        Deploy on demand\n\
+        \n\
+        Obstacles\n\
+        O1. Every deployment waits for manual approval\n\
+        O2. The pipeline cannot check changes\n\
+        O3. Nobody owns the pipeline\n\
+        \n\
+        Objectives\n\
+        IO1. Low-risk changes deploy without approval {overcomes: O1, requires: [IO2]}\n\
+        IO2. Automated criteria run in the pipeline {overcomes: [O2]}\n";
+
+    #[test]
+    fn parses_and_exports_prt() {
+        let tree = parse_prt(PRT).unwrap();
+        assert_eq!(tree.goal, "Deploy on demand");
+        assert_eq!(tree.objectives[&1].requires, vec![2]);
+        assert_eq!(tree.order(), Ok(vec![2, 1]));
+        assert_eq!(tree.open_obstacles(), vec![3]);
+        assert_eq!(parse_prt(&tree.to_string()).unwrap(), tree);
+    }
+
+    #[test]
+    fn rejects_invalid_prts() {
+        let cyclic = PRT.replace("{overcomes: [O2]}", "{overcomes: [O2], requires: IO1}");
+        assert!(parse_prt(&cyclic).unwrap_err().to_string().contains("Objectives IO1, IO2 require each other in a cycle"));
+
+        let unknown = PRT.replace("overcomes: O1", "overcomes: O7");
+        assert!(parse_prt(&unknown).unwrap_err().to_string().contains("overcomes undefined obstacle O7"));
+
+        let missing = PRT.replace("requires: [IO2]", "requires: [IO9]");
+        assert!(parse_prt(&missing).unwrap_err().to_string().contains("requires undefined objective IO9"));
+    }
+
+    #[test]
+    fn drafts_prt_from_planner_output() {
+        let plan: PlannerOutput = serde_json::from_value(serde_json::json!({
+            "executive_summary": {"expected_outcome": "Deploy twice a week"},
+            "ninety_day_plan": {
+                "phase_2_intervention": {
+                    "interventions": [
+                        {"name": "Auto-approve low-risk changes", "constraint_addressed": "Manual approval gate"},
+                        {"name": "Pair on approvals", "constraint_addressed": "Manual approval gate"}
+                    ],
+                    "risks": [{"risk": "Failure rate rises", "mitigation": "Roll back automatically"}]
+                },
+                "phase_1_discovery": {
+                    "objectives": ["Baseline metrics are captured"],
+                    "risks": [{"risk": "Data is unreliable"}]
+                }
+            }
+        }))
+        .unwrap();
+
+        let tree = PrerequisiteTree::from_plan(&plan).unwrap();
+        assert_eq!(tree.goal, "Deploy twice a week");
+        assert_eq!(tree.objectives[&1].text, "Baseline metrics are captured");
+        assert_eq!(tree.objectives[&2].overcomes, vec![2]);
+        assert_eq!(tree.objectives[&3].overcomes, vec![2]);
+        assert_eq!(tree.objectives[&4].overcomes, vec![3]);
+        assert_eq!(tree.objectives[&4].requires, vec![1]);
+        assert_eq!(tree.open_obstacles(), vec![1]);
+        assert_eq!(tree.order(), Ok(vec![1, 2, 3, 4]));
+        assert_eq!(parse_prt(&tree.to_string()).unwrap(), tree);
+    }
+
+    #[test]
+    fn drafts_phases_in_numeric_order() {
+        let phases: serde_json::Map<String, serde_json::Value> = (1..=11)
+            .map(|n| (format!("phase_{n}_step"), serde_json::json!({"objectives": [format!("Step {n} is done")]})))
+            .collect();
+        let plan: PlannerOutput = serde_json::from_value(serde_json::json!({"ninety_day_plan": phases})).unwrap();
+
+        let tree = PrerequisiteTree::from_plan(&plan).unwrap();
+        let texts: Vec<&str> = tree.objectives.values().map(|o| o.text.as_str()).collect();
+        assert_eq!(texts[1], "Step 2 is done");
+        assert_eq!(texts[10], "Step 11 is done");
+        assert_eq!(tree.objectives[&11].requires, vec![10]);
+        assert_eq!(tree.order(), Ok((1..=11).collect()));
+    }
+
+    #[test]
+    fn drafts_exportable_prt_from_hostile_planner_text() {
+        let plan = |risk: &str| -> PlannerOutput {
+            serde_json::from_value(serde_json::json!({
+                "executive_summary": {"expected_outcome": " \n ", "primary_constraint": "Approval\n// gate"},
+                "ninety_day_plan": {
+                    "phase_1": {"objectives": ["Ship {overcomes: [O1]}"]},
+                    "phase_2": {
+                        "interventions": [{"name": "Say \"done\"\tearly", "constraint_addressed": "Wait //forever"}],
+                        "risks": [{"risk": risk, "mitigation": "Line\r\nbreak {requires: IO1}"}]
+                    }
+                }
+            }))
+            .unwrap()
+        };
+
+        let tree = PrerequisiteTree::from_plan(&plan("Paths like a///b")).unwrap();
+        assert_eq!(tree.goal, "Approval / / gate");
+        assert_eq!(tree.objectives[&1].text, "Ship (overcomes: [O1])");
+        assert_eq!(tree.objectives[&2].text, "Say 'done' early");
+        assert_eq!(tree.objectives[&3].text, "Line break (requires: IO1)");
+        assert_eq!(tree.obstacles[&1].text, "Wait / /forever");
+        assert_eq!(tree.obstacles[&2].text, "Paths like a/ / /b");
+        assert_eq!(tree.order(), Ok(vec![1, 2, 3]));
+        assert_eq!(parse_prt(&tree.to_string()).unwrap(), tree);
+
+        let error = PrerequisiteTree::from_plan(&plan(" \n\t ")).unwrap_err();
+        assert_eq!(error.to_string(), "Phase 'phase_2' has an empty risk");
+    }
+}
@@ -0,0 +1,336 @@
+//! "What-if" propagation: the CRT read as a boolean network. Setting some
+//! entities true or false (an entity that is fixed no longer holds) and
+//! following the links shows which other entities flip, and which the tree
+//! cannot decide. Loops read as in `negation`: one nothing outside sustains
+//! stops holding.
+
+use crate::graph::leaves;
+use crate::parser::{Expr, CRT};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// One step of a link: when `condition` holds, each target entity holds, or
+/// no longer holds when the target is negated.
+#[derive(Debug)]
+struct Rule<'a> {
+    condition: &'a Expr,
+    targets: Vec<(u32, bool)>,
+}
+
+fn rules(crt: &CRT) -> Vec<Rule<'_>> {
+    crt.links
+        .values()
+        .flat_map(|link| link.segments.windows(2))
+        .map(|pair| {
+            let mut targets = Vec::new();
+            leaves(&pair[1], false, &mut targets);
+            Rule {
+                condition: &pair[0],
+                targets,
+            }
+        })
+        .collect()
+}
+
+/// Kleene three-valued logic: `None` is unknown.
+fn evaluate(expr: &Expr, values: &BTreeMap<u32, Option<bool>>) -> Option<bool> {
+    match expr {
+        Expr::EntityRef(id) => values.get(id).copied().unwrap_or(Some(true)),
+        Expr::Not(inner) => evaluate(inner, values).map(|value| !value),
+        Expr::And(items) => all(&items.iter().map(|item| evaluate(item, values)).collect::<Vec<_>>()),
+    }
+}
+
+/// The outcome of a what-if run against the current reality: the same
+/// propagation with nothing set.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Simulation {
+    /// Every entity's value: `true` holds, `false` no longer holds, `null`
+    /// when the tree cannot decide.
+    pub values: BTreeMap<u32, Option<bool>>,
+    /// Every entity's value before the change. Targets of `→ NOT` steps whose
+    /// conditions hold start out not holding.
+    pub baseline: BTreeMap<u32, Option<bool>>,
+    /// Entities, other than those set, that held and no longer hold.
+    pub flipped: Vec<u32>,
+    /// Entities, other than those set, that did not hold and now do.
+    pub restored: Vec<u32>,
+    /// Entities the tree cannot decide: forced both ways, negating each other
+    /// in a loop, or downstream of either.
+    pub undetermined: Vec<u32>,
+    /// Undetermined entities one link makes hold and another negates.
+    pub conflicts: Vec<u32>,
+}
+
+/// Propagate `assignments` through `crt` and compare with the propagation
+/// without them. Entities set keep their value. Any other entity holds when a
+/// step leading to it holds; when every such step fails it no longer holds,
+/// and a step into its negation can switch it off. Entities no link leads to
+/// hold unless negated. A loop nothing outside sustains no longer holds, as
+/// in `negate_crt`, which reads the same tree with its `NOT` terms left out.
+pub fn simulate(crt: &CRT, assignments: &BTreeMap<u32, bool>) -> Result<Simulation> {
+    if let Some(id) = assignments.keys().find(|id| !crt.entities.contains_key(id)) {
+        return Err(anyhow!("Entity E{id} is not in the CRT"));
+    }
+    let rules = rules(crt);
+    let (baseline, _) = propagate(crt, &rules, &BTreeMap::new());
+    let (values, conflicts) = propagate(crt, &rules, assignments);
+
+    let changed = |from: bool, to: bool| -> Vec<u32> {
+        values
+            .iter()
+            .filter(|(id, value)| **value == Some(to) && baseline[id] == Some(from) && !assignments.contains_key(id))
+            .map(|(id, _)| *id)
+            .collect()
+    };
+    let undetermined: Vec<u32> = values.iter().filter(|(_, value)| value.is_none()).map(|(id, _)| *id).collect();
+    Ok(Simulation {
+        flipped: changed(true, false),
+        restored: changed(false, true),
+        conflicts: conflicts.into_iter().filter(|id| undetermined.contains(id)).collect(),
+        undetermined,
+        baseline,
+        values,
+    })
+}
+
+/// Every entity's value under `assignments`, and the entities one link makes
+/// hold while another negates them. When the entities left undecided can all
+/// hold together, as in the current reality, they do.
+fn propagate(
+    crt: &CRT,
+    rules: &[Rule],
+    assignments: &BTreeMap<u32, bool>,
+) -> (BTreeMap<u32, Option<bool>>, BTreeSet<u32>) {
+    let (values, conflicts) = settle(crt, rules, assignments);
+    let undecided: Vec<u32> =
+        values.iter().filter(|(id, value)| value.is_none() && !conflicts.contains(id)).map(|(id, _)| *id).collect();
+    if undecided.is_empty() {
+        return (values, conflicts);
+    }
+    let mut assumed = assignments.clone();
+    assumed.extend(undecided.iter().map(|id| (*id, true)));
+    let (trial, trial_conflicts) = settle(crt, rules, &assumed);
+    if undecided.iter().all(|id| derive(*id, rules, &trial) == (Some(true), false)) {
+        (trial, trial_conflicts)
+    } else {
+        (values, conflicts)
+    }
+}
+
+/// The values `assignments` force, with unknowns that could only hold through
+/// each other, such as a loop whose outside support is gone, no longer holding.
+fn settle(
+    crt: &CRT,
+    rules: &[Rule],
+    assignments: &BTreeMap<u32, bool>,
+) -> (BTreeMap<u32, Option<bool>>, BTreeSet<u32>) {
+    let mut values: BTreeMap<u32, Option<bool>> =
+        crt.entities.keys().map(|id| (*id, assignments.get(id).copied())).collect();
+    let free: Vec<u32> = crt.entities.keys().copied().filter(|id| !assignments.contains_key(id)).collect();
+
+    let mut conflicts = BTreeSet::new();
+    loop {
+        // unknowns only ever resolve, so this settles within one pass per entity
+        loop {
+            let mut changed = false;
+            for &id in &free {
+                let (value, conflict) = derive(id, rules, &values);
+                if conflict {
+                    conflicts.insert(id);
+                }
+                if value.is_some() && values[&id] != value {
+                    values.insert(id, value);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let mut possible: BTreeSet<u32> = values.iter().filter(|(_, value)| **value == Some(true)).map(|(id, _)| *id).collect();
+        loop {
+            let found: Vec<u32> = free
+                .iter()
+                .copied()
+                .filter(|id| values[id].is_none() && !possible.contains(id))
+                .filter(|id| {
+                    let mut causes = rules
+                        .iter()
+                        .filter(|rule| rule.targets.iter().any(|(target, negated)| target == id && !negated))
+                        .peekable();
+                    causes.peek().is_none() || causes.any(|rule| could_hold(rule.condition, &values, &possible))
+                })
+                .collect();
+            if found.is_empty() {
+                break;
+            }
+            possible.extend(found);
+        }
+        let unfounded: Vec<u32> = free.iter().copied().filter(|id| values[id].is_none() && !possible.contains(id)).collect();
+        if unfounded.is_empty() {
+            return (values, conflicts);
+        }
+        for id in unfounded {
+            values.insert(id, Some(false));
+        }
+    }
+}
+
+/// The value the steps into `id` give it, and whether one makes it hold while
+/// another negates it.
+fn derive(id: u32, rules: &[Rule], values: &BTreeMap<u32, Option<bool>>) -> (Option<bool>, bool) {
+    let (mut causes, mut negations) = (Vec::new(), Vec::new());
+    for rule in rules {
+        for &(_, negated) in rule.targets.iter().filter(|(target, _)| *target == id) {
+            let condition = evaluate(rule.condition, values);
+            if negated {
+                negations.push(condition);
+            } else {
+                causes.push(condition);
+            }
+        }
+    }
+    let caused = if causes.is_empty() { Some(true) } else { any(&causes) };
+    match any(&negations) {
+        Some(true) if caused == Some(true) && !causes.is_empty() => (None, true),
+        Some(true) => (Some(false), false),
+        Some(false) => (caused, false),
+        None if caused == Some(false) => (Some(false), false),
+        None => (None, false),
+    }
+}
+
+/// Whether `expr` can still come to hold when only the entities in `possible`
+/// might hold. Negated terms are read from the settled `values`.
+fn could_hold(expr: &Expr, values: &BTreeMap<u32, Option<bool>>, possible: &BTreeSet<u32>) -> bool {
+    match expr {
+        Expr::EntityRef(id) => possible.contains(id),
+        Expr::Not(inner) => evaluate(inner, values) != Some(true),
+        Expr::And(items) => items.iter().all(|item| could_hold(item, values, possible)),
+    }
+}
+
+/// Kleene `AND`.
+fn all(values: &[Option<bool>]) -> Option<bool> {
+    if values.contains(&Some(false)) {
+        Some(false)
+    } else if values.contains(&None) {
+        None
+    } else {
+        Some(true)
+    }
+}
+
+/// Kleene `OR`; `false` when there is nothing to join.
+fn any(values: &[Option<bool>]) -> Option<bool> {
+    if values.contains(&Some(true)) {
+        Some(true)
+    } else if values.contains(&None) {
+        None
+    } else {
+        Some(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::negation::negate_crt;
+    use crate::parser::parse_crt;
+
+    const TREE: &str = "Entities\n\
+        E1. Changes need manual approval\n\
+        E2. Tests run only before releases\n\
+        E3. Approvals queue up\n\
+        E4. Releases batch up\n\
+        E5. Failures are hard to trace\n\
+        E6. Customers wait for fixes\n\
+        E7. Audits demand sign-off\n\
+        E8. Support escalates\n\
+        \n\
+        Links\n\
+        L1. E1 → E3 → E4 → E5\n\
+        L2. E4 → E3\n\
+        L3. E2 AND E7 → E6 → E8\n\
+        L4. NOT E2 → NOT E8\n";
+
+    fn simulate_with(assignments: &[(u32, bool)]) -> Simulation {
+        simulate(&parse_crt(TREE).unwrap(), &assignments.iter().copied().collect()).unwrap()
+    }
+
+    #[test]
+    fn propagates_through_and_junctions_and_chains() {
+        let simulation = simulate_with(&[(7, false)]);
+        assert_eq!(simulation.flipped, vec![6, 8]);
+        assert!(simulation.undetermined.is_empty());
+
+        // E6 still needs E2 AND E7, so keeping E7 does not bring it back
+        let simulation = simulate_with(&[(2, false), (7, true)]);
+        assert_eq!(simulation.flipped, vec![6, 8]);
+        assert_eq!(simulation.values[&1], Some(true));
+    }
+
+    #[test]
+    fn flags_undetermined_entities() {
+        // L3 makes E8 hold through E6 while L4 negates it
+        let simulation = simulate_with(&[(2, false), (6, true)]);
+        assert_eq!(simulation.conflicts, vec![8]);
+        assert_eq!(simulation.values[&8], None);
+
+        // each negates the other, so neither can be decided
+        let crt = parse_crt("Entities\nE1. Teams guard scope\nE2. Scope creeps\n\nLinks\nL1. E1 → NOT E2\nL2. E2 → NOT E1\n").unwrap();
+        let simulation = simulate(&crt, &BTreeMap::new()).unwrap();
+        assert_eq!(simulation.undetermined, vec![1, 2]);
+        assert!(simulation.conflicts.is_empty());
+
+        let error = simulate(&parse_crt(TREE).unwrap(), &BTreeMap::from([(9, false)])).unwrap_err();
+        assert!(error.to_string().contains("Entity E9 is not in the CRT"));
+    }
+
+    #[test]
+    fn compares_against_the_tree_with_nothing_set() {
+        let crt = parse_crt(
+            "Entities\n\
+             E1. Releases are frozen\n\
+             E2. Fixes ship\n\
+             E3. Customers wait for fixes\n\
+             E4. Hotfix branches pile up\n\
+             E5. Merges conflict\n\
+             \n\
+             Links\n\
+             L1. E1 → NOT E2\n\
+             L2. NOT E2 → E3\n\
+             L3. NOT E1 → NOT E4 → NOT E5\n",
+        )
+        .unwrap();
+
+        let simulation = simulate(&crt, &BTreeMap::from([(1, false)])).unwrap();
+        assert_eq!(simulation.baseline[&2], Some(false));
+        assert_eq!(simulation.restored, vec![2]);
+        assert_eq!(simulation.flipped, vec![3, 4, 5]);
+        assert!(simulation.undetermined.is_empty());
+    }
+
+    #[test]
+    fn loses_loops_as_negation_does() {
+        // once E1 is fixed, nothing outside their loop keeps E3 and E4 going
+        let simulation = simulate_with(&[(1, false)]);
+        assert_eq!(simulation.flipped, vec![3, 4, 5]);
+        assert!(simulation.undetermined.is_empty());
+
+        // without NOT terms, fixing an entity is injecting against it
+        let crt = parse_crt(
+            "Entities\nE1. A\nE2. B\nE3. C\nE4. D\nE5. E\nE6. F\n\n\
+             Links\nL1. E1 → E3 → E4 → E5\nL2. E4 → E3\nL3. E2 AND E5 → E6\n",
+        )
+        .unwrap();
+        for &id in crt.entities.keys() {
+            let simulation = simulate(&crt, &BTreeMap::from([(id, false)])).unwrap();
+            let lost: Vec<u32> = simulation.values.iter().filter(|(_, value)| **value == Some(false)).map(|(id, _)| *id).collect();
+            assert_eq!(lost, negate_crt(&crt, &[id]).unwrap().neutralised, "fixing E{id}");
+        }
+    }
+}
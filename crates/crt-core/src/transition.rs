@@ -0,0 +1,164 @@
+//! Transition Trees: the steps that reach an objective, each an action with
+//! the need it answers and the effect it is predicted to have.
+
+use crate::parser::{
+    attribute_scalar, document_text, grammar_diagnostic, line_terminated, parse_statement_line, unknown_attribute, CRTParser,
+    ParseDiagnostic, Rule, Statement,
+};
+use anyhow::{anyhow, Result};
+use pest::iterators::Pair;
+use pest::Parser;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TransitionStep {
+    pub id: u32,
+    pub action: String,
+    /// Why the step is taken: the need or the reality it answers.
+    pub need: Option<String>,
+    /// What the action is predicted to bring about.
+    pub effect: Option<String>,
+    pub owner: Option<String>,
+}
+
+/// Keys accepted in a step's `{...}` block.
+pub const STEP_ATTRIBUTE_KEYS: &[&str] = &["need", "effect", "owner"];
+
+/// Steps run in id order; each step's effect sets up the next.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct TransitionTree {
+    pub objective: String,
+    pub steps: BTreeMap<u32, TransitionStep>,
+}
+
+impl TransitionTree {
+    /// Steps missing a need or an effect, which leave the logic of the sequence unstated.
+    pub fn incomplete_steps(&self) -> Vec<u32> {
+        self.steps
+            .values()
+            .filter(|step| step.need.is_none() || step.effect.is_none())
+            .map(|step| step.id)
+            .collect()
+    }
+}
+
+impl fmt::Display for TransitionTree {
+    /// The tree in the grammar `parse_transition_tree` reads.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Objective\n{}\n\nSteps", document_text(&self.objective))?;
+        for step in self.steps.values() {
+            write!(f, "S{}. {}", step.id, document_text(&step.action))?;
+            // an empty value would not parse back, so it is left out like a missing one
+            let attributes: Vec<String> = [("need", &step.need), ("effect", &step.effect), ("owner", &step.owner)]
+                .into_iter()
+                .filter_map(|(key, value)| Some((key, document_text(value.as_ref()?))))
+                .filter(|(_, value)| !value.is_empty())
+                .map(|(key, value)| format!("{key}: \"{value}\""))
+                .collect();
+            if !attributes.is_empty() {
+                write!(f, " {{{}}}", attributes.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// ---------- API ----------
+pub fn parse_transition_tree(input: &str) -> Result<TransitionTree> {
+    let source = line_terminated(input);
+    let mut pairs = CRTParser::parse(Rule::transition_file, source.as_ref()).map_err(grammar_diagnostic)?;
+    let file = pairs.next().unwrap();
+
+    let mut tree = TransitionTree::default();
+    for line in file.into_inner() {
+        match line.as_rule() {
+            Rule::heading_text => tree.objective = line.as_str().trim().to_string(),
+            Rule::step_line => {
+                let step = parse_step_line(line.clone()).map_err(|e| ParseDiagnostic::at(&line, e))?;
+                let id = step.id;
+                if tree.steps.insert(id, step).is_some() {
+                    return Err(ParseDiagnostic::at(&line, format!("Duplicate step S{id}")).into());
+                }
+            }
+            // headings/blanklines/whitespace are already consumed in the grammar
+            _ => {}
+        }
+    }
+    Ok(tree)
+}
+
+// ---------- parsers ----------
+fn parse_step_line(p: Pair<Rule>) -> Result<TransitionStep> {
+    let Statement { id, text, attributes } = parse_statement_line(p)?;
+    let mut step = TransitionStep {
+        id,
+        action: text,
+        need: None,
+        effect: None,
+        owner: None,
+    };
+    for (key, value) in attributes {
+        let slot = match key.as_str() {
+            "need" => &mut step.need,
+            "effect" => &mut step.effect,
+            "owner" => &mut step.owner,
+            _ => return Err(unknown_attribute(&format!("step attribute '{key}'"), STEP_ATTRIBUTE_KEYS)),
+        };
+        *slot = Some(attribute_scalar(&key, value).map_err(|e| anyhow!("Step S{id}: {e}"))?);
+    }
+    Ok(step)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_exports_transition_tree() {
+        let tree = parse_transition_tree(
+            "Objective\n\
+             Automated criteria run in the pipeline\n\
+             \n\
+             Steps\n\
+             S1. Write down the approval criteria {need: \"Nobody agrees what low risk means\", effect: Criteria are shared}\n\
+             S2. Encode the criteria as pipeline checks {effect: \"Checks flag risky changes\", owner: platform}\n",
+        )
+        .unwrap();
+
+        assert_eq!(tree.objective, "Automated criteria run in the pipeline");
+        assert_eq!(tree.steps[&1].need.as_deref(), Some("Nobody agrees what low risk means"));
+        assert_eq!(tree.steps[&2].owner.as_deref(), Some("platform"));
+        assert_eq!(tree.incomplete_steps(), vec![2]);
+        assert_eq!(parse_transition_tree(&tree.to_string()).unwrap(), tree);
+
+        let error = parse_transition_tree("Objective\nShip\n\nSteps\nS1. Act {why: now}\n").unwrap_err();
+        assert!(error.to_string().contains("Unknown step attribute 'why' (expected need, effect, owner)"));
+    }
+
+    #[test]
+    fn exports_hostile_text_that_parses_back() {
+        let step = |action: &str, need: &str, owner: &str| TransitionStep {
+            id: 1,
+            action: action.to_string(),
+            need: Some(need.to_string()),
+            effect: None,
+            owner: Some(owner.to_string()),
+        };
+        let tree = TransitionTree {
+            objective: "Checks run\nin the // pipeline".to_string(),
+            steps: BTreeMap::from([(1, step("Act {owner: \"ops\"}", "Say \"now\"", " \t "))]),
+        };
+
+        let parsed = parse_transition_tree(&tree.to_string()).unwrap();
+        assert_eq!(parsed.objective, "Checks run in the / / pipeline");
+        // the blank owner is left out rather than written as an empty value
+        let expected = TransitionStep {
+            owner: None,
+            ..step("Act (owner: 'ops')", "Say 'now'", "")
+        };
+        assert_eq!(parsed.steps[&1], expected);
+    }
+}
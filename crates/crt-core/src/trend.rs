@@ -0,0 +1,180 @@
+//! Trends over dated metric observations: least-squares slope, percentage
+//! change and volatility, with a direction read against the catalogue.
+
+use crate::catalogue::MetricCatalogue;
+use crate::dora::convert_unit;
+use crate::types::{DoraMetric, MetricHistory, MetricSeries};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// Fitted change over the series, as a percentage of its mean, below which a
+/// metric counts as stable.
+pub const STABLE_CHANGE_PERCENT: f32 = 5.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrendDirection {
+    Improving,
+    Stable,
+    Worsening,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MetricTrend {
+    pub metric: String,
+    pub unit: String,
+    pub observations: usize,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub latest: f32,
+    /// Least-squares slope in `unit` per day.
+    pub slope_per_day: f32,
+    /// Change from the first to the latest observation, or `None` when the
+    /// first is zero.
+    pub percent_change: Option<f32>,
+    /// Standard deviation as a percentage of the mean (0 when the mean is 0).
+    pub volatility: f32,
+    pub direction: TrendDirection,
+}
+
+fn round(value: f64) -> f32 {
+    ((value * 1000.0).round() / 1000.0) as f32
+}
+
+/// Trend of `series`, converted into the catalogue unit of `name` when it has
+/// one. Needs at least two observations.
+pub fn compute_trend(catalogue: &MetricCatalogue, name: &str, series: &MetricSeries) -> Option<MetricTrend> {
+    let config = catalogue.get(name);
+    let target = config
+        .map(|config| config.unit.as_ref())
+        .filter(|unit| convert_unit(1.0, &series.unit, unit).is_some())
+        .unwrap_or(&series.unit);
+    let mut points: Vec<(NaiveDate, f64)> = series
+        .observations
+        .iter()
+        .filter_map(|observation| Some((observation.date, convert_unit(observation.value, &series.unit, target)? as f64)))
+        .collect();
+    points.sort_by_key(|(date, _)| *date);
+    let (&(from, first), &(to, latest)) = (points.first()?, points.last()?);
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let xs: Vec<f64> = points.iter().map(|(date, _)| (*date - from).num_days() as f64).collect();
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = xs.iter().zip(&points).map(|(x, (_, y))| (x - mean_x) * (y - mean_y)).sum();
+    let slope = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+    let deviation = (points.iter().map(|(_, y)| (y - mean_y).powi(2)).sum::<f64>() / n).sqrt();
+
+    let fitted_change = slope * xs.last().copied().unwrap_or_default();
+    let relative = if mean_y != 0.0 { 100.0 * fitted_change / mean_y.abs() } else { 100.0 * fitted_change.signum() };
+    let lower_is_better = config.is_some_and(|config| config.inverted);
+    let direction = if relative.abs() < f64::from(STABLE_CHANGE_PERCENT) {
+        TrendDirection::Stable
+    } else if (slope > 0.0) != lower_is_better {
+        TrendDirection::Improving
+    } else {
+        TrendDirection::Worsening
+    };
+
+    Some(MetricTrend {
+        metric: name.to_string(),
+        unit: target.to_string(),
+        observations: points.len(),
+        from,
+        to,
+        latest: round(latest),
+        slope_per_day: round(slope),
+        percent_change: (first != 0.0).then(|| round(100.0 * (latest - first) / first.abs())),
+        volatility: if mean_y != 0.0 { round(100.0 * deviation / mean_y.abs()) } else { 0.0 },
+        direction,
+    })
+}
+
+/// Trends of every series in `history` with at least two observations.
+pub fn compute_trends(catalogue: &MetricCatalogue, history: &MetricHistory) -> Vec<MetricTrend> {
+    history
+        .iter()
+        .filter_map(|(name, series)| compute_trend(catalogue, name, series))
+        .collect()
+}
+
+/// Overlay the observations of `from` onto `into`. Series missing from `into`
+/// are copied; observations whose unit cannot be converted are skipped.
+pub fn merge_history(into: &mut MetricHistory, from: &MetricHistory) {
+    for (name, series) in from {
+        let target = into.entry(name.clone()).or_insert_with(|| MetricSeries::new(series.unit.clone()));
+        for observation in &series.observations {
+            let metric = DoraMetric {
+                value: observation.value,
+                unit: series.unit.clone(),
+            };
+            let _ = target.record_metric(observation.date, &metric);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn series(unit: &str, values: &[(u32, f32)]) -> MetricSeries {
+        let mut series = MetricSeries::new(unit);
+        for (day, value) in values {
+            series.record(date(*day), *value);
+        }
+        series
+    }
+
+    #[test]
+    fn falling_lead_time_is_improving() {
+        let catalogue = MetricCatalogue::builtin();
+        let lead_time = series("hours", &[(15, 48.0), (1, 96.0), (29, 24.0)]);
+        let trend = compute_trend(&catalogue, "lead_time", &lead_time).unwrap();
+
+        assert_eq!(trend.unit, "days");
+        assert_eq!((trend.from, trend.to, trend.observations), (date(1), date(29), 3));
+        assert_eq!(trend.latest, 1.0);
+        // 4, 2, 1 days at day 0, 14, 28
+        assert_eq!(trend.slope_per_day, -0.107);
+        assert_eq!(trend.percent_change, Some(-75.0));
+        assert_eq!(trend.volatility, 53.452);
+        assert_eq!(trend.direction, TrendDirection::Improving);
+    }
+
+    #[test]
+    fn direction_follows_catalogue_and_threshold() {
+        let catalogue = MetricCatalogue::builtin();
+        let rising = series("deployments/day", &[(1, 1.0), (8, 2.0)]);
+        assert_eq!(compute_trend(&catalogue, "deployment_frequency", &rising).unwrap().direction, TrendDirection::Improving);
+
+        let failing = series("%", &[(1, 10.0), (8, 20.0)]);
+        assert_eq!(compute_trend(&catalogue, "change_failure_rate", &failing).unwrap().direction, TrendDirection::Worsening);
+
+        let flat = series("%", &[(1, 20.0), (8, 20.5)]);
+        assert_eq!(compute_trend(&catalogue, "change_failure_rate", &flat).unwrap().direction, TrendDirection::Stable);
+
+        assert!(compute_trend(&catalogue, "mttr", &series("days", &[(1, 1.0)])).is_none());
+    }
+
+    #[test]
+    fn merges_and_replaces_same_day_observations() {
+        let mut history = MetricHistory::new();
+        history.insert("mttr".to_string(), series("days", &[(1, 1.0), (2, 2.0)]));
+        let mut update = MetricHistory::new();
+        update.insert("mttr".to_string(), series("hours", &[(2, 12.0), (3, 6.0)]));
+        update.insert("lead_time".to_string(), series("days", &[(3, 4.0)]));
+
+        merge_history(&mut history, &update);
+        let values: Vec<f32> = history["mttr"].observations.iter().map(|o| o.value).collect();
+        assert_eq!(values, vec![1.0, 0.5, 0.25]);
+        assert_eq!(history["lead_time"].unit, "days");
+    }
+}
@@ -1,6 +1,19 @@
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Deserialize, Serialize)]
+use crate::cloud::EvaporatingCloud;
+use crate::cutsets::CutSets;
+use crate::dora::DoraProfile;
+use crate::evidence::EvidenceMap;
+use crate::graph::CausalPath;
+use crate::leverage::EntityLeverage;
+use crate::negation::FrtSkeleton;
+use crate::parser::{EntityAttributes, LinkAnnotation};
+use crate::trend::MetricTrend;
+use crate::westrum::{WestrumCulture, WestrumSummary, WestrumSurvey};
+use chrono::NaiveDate;
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DoraMetrics {
     pub deployment_frequency: f32,
     pub lead_time: f32,
@@ -8,6 +21,83 @@ pub struct DoraMetrics {
     pub mttr: f32,
 }
 
+impl DoraMetrics {
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, f32)> {
+        [
+            ("deployment_frequency", self.deployment_frequency),
+            ("lead_time", self.lead_time),
+            ("change_failure_rate", self.change_failure_rate),
+            ("mttr", self.mttr),
+        ]
+        .into_iter()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawDoraMetrics {
+    pub deployment_frequency: DoraMetric,
+    pub lead_time: DoraMetric,
+    pub change_failure_rate: DoraMetric,
+    pub mttr: DoraMetric,
+}
+
+impl RawDoraMetrics {
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &DoraMetric)> {
+        [
+            ("deployment_frequency", &self.deployment_frequency),
+            ("lead_time", &self.lead_time),
+            ("change_failure_rate", &self.change_failure_rate),
+            ("mttr", &self.mttr),
+        ]
+        .into_iter()
+    }
+}
+
+/// DORA metrics either as 0–1 slider positions or as real values with units,
+/// e.g. `{"lead_time": {"value": 3.5, "unit": "days"}}`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum DoraMetricsInput {
+    Sliders(DoraMetrics),
+    Values(RawDoraMetrics),
+}
+
+/// Values when any metric is an object, sliders otherwise. Errors name the
+/// metric at fault rather than failing to match either form.
+impl<'de> Deserialize<'de> for DoraMetricsInput {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = serde_json::Map::deserialize(deserializer)?;
+        fn field<T: DeserializeOwned, E: de::Error>(fields: &serde_json::Map<String, serde_json::Value>, name: &'static str) -> Result<T, E> {
+            let value = fields.get(name).ok_or_else(|| E::missing_field(name))?;
+            T::deserialize(value).map_err(|e| E::custom(format!("{name}: {e}")))
+        }
+        if fields.values().any(serde_json::Value::is_object) {
+            Ok(DoraMetricsInput::Values(RawDoraMetrics {
+                deployment_frequency: field(&fields, "deployment_frequency")?,
+                lead_time: field(&fields, "lead_time")?,
+                change_failure_rate: field(&fields, "change_failure_rate")?,
+                mttr: field(&fields, "mttr")?,
+            }))
+        } else {
+            Ok(DoraMetricsInput::Sliders(DoraMetrics {
+                deployment_frequency: field(&fields, "deployment_frequency")?,
+                lead_time: field(&fields, "lead_time")?,
+                change_failure_rate: field(&fields, "change_failure_rate")?,
+                mttr: field(&fields, "mttr")?,
+            }))
+        }
+    }
+}
+
+/// A catalogue metric reported either as a 0–1 slider position or as a real
+/// value with its unit.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum MetricInput {
+    Slider(f32),
+    Value(DoraMetric),
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct EngineeringMetrics {
     pub commit_frequency: f32,
@@ -15,7 +105,7 @@ pub struct EngineeringMetrics {
     pub pbis_delivered_per_sprint_per_team: f32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct TimeAllocation {
     pub meetings: i32,
     pub unplanned: i32,
@@ -24,21 +114,151 @@ pub struct TimeAllocation {
     pub tech_debt: i32,
 }
 
+impl TimeAllocation {
+    /// How far the buckets may sum from 100, absorbing rounding in the form.
+    pub const TOLERANCE: i32 = 1;
+
+    pub const BUCKETS: [&'static str; 5] = ["meetings", "unplanned", "bugs", "feature", "tech_debt"];
+
+    pub fn buckets(&self) -> [(&'static str, i32); 5] {
+        [
+            ("meetings", self.meetings),
+            ("unplanned", self.unplanned),
+            ("bugs", self.bugs),
+            ("feature", self.feature),
+            ("tech_debt", self.tech_debt),
+        ]
+    }
+
+    pub fn total(&self) -> i32 {
+        self.buckets().iter().map(|(_, value)| value).sum()
+    }
+
+    pub fn is_total_valid(&self) -> bool {
+        (self.total() - 100).abs() <= Self::TOLERANCE
+    }
+
+    /// Percentages proportional to `weights` (in `BUCKETS` order), rounded by
+    /// the largest remainder method so they sum to exactly 100. `None` when
+    /// no weight is positive.
+    pub fn from_weights(weights: [f32; 5]) -> Option<Self> {
+        let weights = weights.map(|w| if w.is_finite() { w.max(0.0) } else { 0.0 });
+        let total: f32 = weights.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let quotas = weights.map(|w| w / total * 100.0);
+        let mut percentages = quotas.map(|q| q.floor() as i32);
+        let mut by_remainder: Vec<usize> = (0..quotas.len()).collect();
+        by_remainder.sort_by(|a, b| (quotas[*b] - quotas[*b].floor()).total_cmp(&(quotas[*a] - quotas[*a].floor())));
+        let shortfall = 100 - percentages.iter().sum::<i32>();
+        for index in by_remainder.into_iter().take(shortfall.max(0) as usize) {
+            percentages[index] += 1;
+        }
+
+        let [meetings, unplanned, bugs, feature, tech_debt] = percentages;
+        Some(TimeAllocation {
+            meetings,
+            unplanned,
+            bugs,
+            feature,
+            tech_debt,
+        })
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AnalyseRequest {
     pub crt: String,
-    pub dora_metrics: DoraMetrics,
+    pub dora_metrics: DoraMetricsInput,
     pub extended_engineering_metrics: EngineeringMetrics,
-    pub westrum: f32,
+    #[serde(default)]
+    pub westrum: Option<f32>,
+    #[serde(default)]
+    pub westrum_survey: Option<WestrumSurvey>,
     pub time_allocation: TimeAllocation,
+    #[serde(default)]
+    pub dora_benchmark: Option<String>,
+    /// Further metrics from the metric catalogue, keyed by name.
+    #[serde(default)]
+    pub metrics: BTreeMap<String, MetricInput>,
+    /// Team the metrics belong to; stored history is kept per team.
+    #[serde(default)]
+    pub team: Option<String>,
+    /// Earlier observations of any catalogue metric, keyed by name.
+    #[serde(default)]
+    pub history: MetricHistory,
+    /// Send trend summaries over the stored and supplied history to the agents.
+    #[serde(default)]
+    pub include_trends: bool,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DoraMetric {
     pub value: f32,
     pub unit: String,
 }
 
+/// One dated value of a metric, in the unit of its series.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MetricObservation {
+    pub date: NaiveDate,
+    pub value: f32,
+}
+
+/// Dated observations of one metric, oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct MetricSeries {
+    pub unit: String,
+    pub observations: Vec<MetricObservation>,
+}
+
+impl MetricSeries {
+    pub fn new(unit: impl Into<String>) -> Self {
+        MetricSeries {
+            unit: unit.into(),
+            observations: Vec::new(),
+        }
+    }
+
+    /// Add the observation for `date`, replacing any earlier one for the same
+    /// day and keeping dates ascending.
+    pub fn record(&mut self, date: NaiveDate, value: f32) {
+        match self.observations.binary_search_by_key(&date, |observation| observation.date) {
+            Ok(index) => self.observations[index].value = value,
+            Err(index) => self.observations.insert(index, MetricObservation { date, value }),
+        }
+    }
+
+    /// Record `metric`, converting it into this series' unit.
+    pub fn record_metric(&mut self, date: NaiveDate, metric: &DoraMetric) -> Result<(), String> {
+        let value = crate::dora::convert_unit(metric.value, &metric.unit, &self.unit)
+            .ok_or_else(|| format!("Cannot convert '{}' to '{}'", metric.unit, self.unit))?;
+        self.record(date, value);
+        Ok(())
+    }
+}
+
+/// Metric series keyed by metric name.
+pub type MetricHistory = BTreeMap<String, MetricSeries>;
+
+/// Observations for one day, as posted to `/api/metrics/history`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MetricHistoryUpdate {
+    #[serde(default)]
+    pub team: Option<String>,
+    pub date: NaiveDate,
+    pub metrics: BTreeMap<String, DoraMetric>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MetricHistoryResponse {
+    pub team: String,
+    pub history: MetricHistory,
+    pub trends: Vec<MetricTrend>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CoreSystemicIssue {
     pub issue: String,
@@ -88,6 +308,20 @@ pub struct EvaluationResponse {
     pub result: EvaluationResult,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlanRequest {
+    pub original_payload: AnalyseRequest,
+    pub analysis_result: AnalysisResult,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlanResponse {
+    pub run_id: String,
+    /// `None` when the planner's output was not plan JSON.
+    pub plan: Option<PlannerOutput>,
+    pub output_text: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AnalyseWithFeedbackRequest {
     pub original_payload: AnalyseRequest,
@@ -107,6 +341,52 @@ pub struct RefineResponse {
     pub structured_response: Option<serde_json::Value>,
 }
 
+/// Asks the cloud agent for the Evaporating Cloud behind a root cause of the CRT.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CloudRequest {
+    pub crt: String,
+    /// Entity to build the conflict around; the core root cause of the tree when absent.
+    #[serde(default)]
+    pub root_cause: Option<u32>,
+    /// A draft cloud document for the agent to complete.
+    #[serde(default)]
+    pub cloud: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CloudResponse {
+    pub run_id: String,
+    pub root_cause: u32,
+    /// The cloud the agent answered with, when it could be read.
+    pub cloud: Option<EvaporatingCloud>,
+    /// `cloud` exported as a cloud document.
+    pub document: Option<String>,
+    pub output_text: String,
+}
+
+/// A Future Reality Tree document to check against the CRT it changes.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FrtCheckRequest {
+    pub crt: String,
+    pub frt: String,
+}
+
+/// Asks for the Future Reality Tree skeleton left by removing some root causes.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NegateRequest {
+    pub crt: String,
+    /// Entities to inject against, usually root causes.
+    pub injected: Vec<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NegateResponse {
+    #[serde(flatten)]
+    pub skeleton: FrtSkeleton,
+    /// `skeleton.frt` exported as an FRT document.
+    pub document: String,
+}
+
 // Evaluation Response Types (based on analysis_evaluator.json schema)
 #[derive(Debug, Deserialize, Serialize)]
 pub struct EvaluationMetadata {
@@ -342,13 +622,88 @@ pub struct EvaluationResult {
     pub review_confidence_assessment: ReviewConfidenceAssessment,
 }
 
+// Planner Output Types (the parts of planner.yml's schema that drafts are built from)
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PlannerOutput {
+    #[serde(default)]
+    pub executive_summary: Option<PlanSummary>,
+    /// Phases keyed by name, e.g. `phase_1_discovery`; `phases` puts them in order.
+    #[serde(default)]
+    pub ninety_day_plan: BTreeMap<String, PlanPhase>,
+}
+
+impl PlannerOutput {
+    /// The phases in the order of the first number in their names, so
+    /// `phase_10_scale` follows `phase_2_pilot`. Phases without a number
+    /// come last, by name.
+    pub fn phases(&self) -> Vec<(&str, &PlanPhase)> {
+        let number = |name: &str| -> Option<u64> {
+            let digits: String =
+                name.chars().skip_while(|c| !c.is_ascii_digit()).take_while(char::is_ascii_digit).collect();
+            digits.parse().ok()
+        };
+        let mut phases: Vec<(&str, &PlanPhase)> =
+            self.ninety_day_plan.iter().map(|(name, phase)| (name.as_str(), phase)).collect();
+        phases.sort_by_key(|(name, _)| (number(name).is_none(), number(name), *name));
+        phases
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PlanSummary {
+    #[serde(default)]
+    pub primary_constraint: Option<String>,
+    #[serde(default)]
+    pub intervention_strategy: Option<String>,
+    #[serde(default)]
+    pub expected_outcome: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PlanPhase {
+    #[serde(default)]
+    pub objectives: Vec<String>,
+    #[serde(default)]
+    pub interventions: Vec<PlanIntervention>,
+    #[serde(default)]
+    pub risks: Vec<PlanRisk>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PlanIntervention {
+    #[serde(default)]
+    pub intervention_id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub constraint_addressed: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PlanRisk {
+    pub risk: String,
+    #[serde(default)]
+    pub mitigation: Option<String>,
+}
+
 // Agent Request Types
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AnalyserRequest {
     pub crt: String,
     pub dora_metrics: std::collections::HashMap<String, DoraMetric>,
     pub extended_engineering_metrics: std::collections::HashMap<String, DoraMetric>,
+    pub metrics: std::collections::HashMap<String, DoraMetric>,
     pub westrum: Option<f32>,
+    pub westrum_culture: Option<WestrumCulture>,
+    pub westrum_survey: Option<WestrumSummary>,
+    pub dora_tiers: DoraProfile,
+    pub evidence: EvidenceMap,
+    pub entity_attributes: BTreeMap<u32, EntityAttributes>,
+    pub link_annotations: BTreeMap<u32, LinkAnnotation>,
+    /// For each final effect, its most confident causal chain from a root cause.
+    pub causal_paths: Vec<CausalPath>,
+    /// Entities ranked by structural leverage, highest first.
+    pub leverage: Vec<EntityLeverage>,
+    pub trends: Option<Vec<MetricTrend>>,
     pub time_allocation: TimeAllocation,
 }
 
@@ -357,7 +712,19 @@ pub struct AnalyserWithFeedbackRequest {
     pub crt: String,
     pub dora_metrics: std::collections::HashMap<String, DoraMetric>,
     pub extended_engineering_metrics: std::collections::HashMap<String, DoraMetric>,
+    pub metrics: std::collections::HashMap<String, DoraMetric>,
     pub westrum: Option<f32>,
+    pub westrum_culture: Option<WestrumCulture>,
+    pub westrum_survey: Option<WestrumSummary>,
+    pub dora_tiers: DoraProfile,
+    pub evidence: EvidenceMap,
+    pub entity_attributes: BTreeMap<u32, EntityAttributes>,
+    pub link_annotations: BTreeMap<u32, LinkAnnotation>,
+    /// For each final effect, its most confident causal chain from a root cause.
+    pub causal_paths: Vec<CausalPath>,
+    /// Entities ranked by structural leverage, highest first.
+    pub leverage: Vec<EntityLeverage>,
+    pub trends: Option<Vec<MetricTrend>>,
     pub time_allocation: TimeAllocation,
     pub analysis_result: AnalysisResult,
     pub evaluation: EvaluationResult,
@@ -368,9 +735,46 @@ pub struct EvaluatorRequest {
     pub current_reality_tree: String,
     pub dora_metrics: std::collections::HashMap<String, DoraMetric>,
     pub extended_engineering_metrics: std::collections::HashMap<String, DoraMetric>,
+    pub metrics: std::collections::HashMap<String, DoraMetric>,
+    pub westrum_score: Option<f32>,
+    pub westrum_culture: Option<WestrumCulture>,
+    pub westrum_survey: Option<WestrumSummary>,
+    pub dora_tiers: DoraProfile,
+    pub evidence: EvidenceMap,
+    pub entity_attributes: BTreeMap<u32, EntityAttributes>,
+    pub link_annotations: BTreeMap<u32, LinkAnnotation>,
+    /// For each final effect, its most confident causal chain from a root cause.
+    pub causal_paths: Vec<CausalPath>,
+    /// Entities ranked by structural leverage, highest first.
+    pub leverage: Vec<EntityLeverage>,
+    pub trends: Option<Vec<MetricTrend>>,
+    pub time_allocation: TimeAllocation,
+    pub analysis_result: AnalysisResult,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlannerRequest {
+    pub current_reality_tree: String,
+    pub dora_metrics: std::collections::HashMap<String, DoraMetric>,
+    pub extended_engineering_metrics: std::collections::HashMap<String, DoraMetric>,
+    pub metrics: std::collections::HashMap<String, DoraMetric>,
     pub westrum_score: Option<f32>,
     pub time_allocation: TimeAllocation,
     pub analysis_result: AnalysisResult,
+    /// For each UDE, the cheapest minimal sets of entities whose removal
+    /// stops it.
+    pub cut_sets: Vec<CutSets>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CloudAgentRequest {
+    pub crt: String,
+    pub root_cause: u32,
+    pub root_cause_text: String,
+    /// Final effects the root cause leads to.
+    pub effects: Vec<u32>,
+    /// The draft to complete, with the root cause as D when none was given.
+    pub cloud: EvaporatingCloud,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
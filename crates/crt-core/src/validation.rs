@@ -1,100 +1,802 @@
+use crate::catalogue::MetricCatalogue;
+use crate::cloud::{core_root_cause, parse_cloud};
+use crate::frt::parse_frt;
+use crate::dora::{convert_unit, find_dora_benchmark, DORA_BENCHMARKS};
+use crate::error::ApiError;
+use crate::parser::{parse_crt, ParseDiagnostic};
 use crate::types::*;
+use crate::westrum::{self, WESTRUM_MAX, WESTRUM_MIN};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Size limits applied to the CRT carried by a request, so oversized trees are
+/// rejected before they are sent to the agents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationLimits {
+    pub max_entities: usize,
+    pub max_links: usize,
+    pub max_entity_text_len: usize,
+}
+
+impl Default for ValidationLimits {
+    fn default() -> Self {
+        ValidationLimits {
+            max_entities: 200,
+            max_links: 400,
+            max_entity_text_len: 300,
+        }
+    }
+}
+
+/// Everything requests are validated against: the CRT size limits and the
+/// metric catalogue that named metrics must appear in.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationContext {
+    pub limits: ValidationLimits,
+    pub catalogue: Arc<MetricCatalogue>,
+}
 
 pub trait Validate {
-    fn validate(&self) -> Result<(), String>;
+    /// Push every violation found in `self` onto `errors`.
+    fn collect_errors(&self, context: &ValidationContext, errors: &mut Vec<ApiError>);
+
+    /// Fail with the first violation found, using the default limits and catalogue.
+    fn validate(&self) -> Result<(), ApiError> {
+        let mut errors = Vec::new();
+        self.collect_errors(&ValidationContext::default(), &mut errors);
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Fail with every violation found, wrapped in `ApiError::ValidationFailed`,
+    /// so forms can show all problems at once.
+    fn validate_all(&self) -> Result<(), ApiError> {
+        self.validate_all_with(&ValidationContext::default())
+    }
+
+    fn validate_all_with(&self, context: &ValidationContext) -> Result<(), ApiError> {
+        let mut errors = Vec::new();
+        self.collect_errors(context, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::ValidationFailed { errors })
+        }
+    }
+}
+
+/// Parse `crt` and check it against the context's limits and catalogue,
+/// reporting under `field`.
+pub fn collect_crt_errors(field: &str, crt: &str, context: &ValidationContext, errors: &mut Vec<ApiError>) {
+    let limits = &context.limits;
+    if crt.trim().is_empty() {
+        errors.push(ApiError::validation(field, "CRT is required"));
+        return;
+    }
+
+    let tree = match parse_crt(crt) {
+        Ok(tree) => tree,
+        Err(e) => {
+            errors.push(parse_error(field, e));
+            return;
+        }
+    };
+
+    if tree.entities.len() > limits.max_entities {
+        errors.push(ApiError::validation(
+            field,
+            format!("CRT has {} entities (max {})", tree.entities.len(), limits.max_entities),
+        ));
+    }
+    if tree.links.len() > limits.max_links {
+        errors.push(ApiError::validation(
+            field,
+            format!("CRT has {} links (max {})", tree.links.len(), limits.max_links),
+        ));
+    }
+    for entity in tree.entities.values() {
+        let len = entity.text.chars().count();
+        if len > limits.max_entity_text_len {
+            errors.push(ApiError::validation(
+                field,
+                format!("Entity E{} text is {len} characters (max {})", entity.id, limits.max_entity_text_len),
+            ));
+        }
+        for metric in entity.metrics.iter().filter(|metric| context.catalogue.get(metric).is_none()) {
+            errors.push(ApiError::validation(field, format!("Entity E{} is tagged with unknown metric '{metric}'", entity.id)));
+        }
+    }
+}
+
+fn parse_error(field: &str, e: anyhow::Error) -> ApiError {
+    let diagnostics: Vec<ParseDiagnostic> = e.downcast_ref::<ParseDiagnostic>().cloned().into_iter().collect();
+    ApiError::InvalidCrt {
+        field: field.to_string(),
+        message: e.to_string(),
+        diagnostics,
+    }
+}
+
+/// Parse the Evaporating Cloud document `cloud` and check its texts against
+/// the entity text limit, reporting under `field`.
+pub fn collect_cloud_errors(field: &str, cloud: &str, context: &ValidationContext, errors: &mut Vec<ApiError>) {
+    let cloud = match parse_cloud(cloud) {
+        Ok(cloud) => cloud,
+        Err(e) => {
+            errors.push(parse_error(field, e));
+            return;
+        }
+    };
+
+    let max = context.limits.max_entity_text_len;
+    for (cloud_box, text) in &cloud.boxes {
+        let len = text.chars().count();
+        if len > max {
+            errors.push(ApiError::validation(field, format!("Box {cloud_box} text is {len} characters (max {max})")));
+        }
+    }
+    for (arrow, assumptions) in &cloud.assumptions {
+        for len in assumptions.iter().map(|text| text.chars().count()).filter(|len| *len > max) {
+            errors.push(ApiError::validation(field, format!("Assumption on {arrow} is {len} characters (max {max})")));
+        }
+    }
+}
+
+/// Parse the Future Reality Tree document `frt` and check it against the
+/// context's limits, reporting under `field`.
+pub fn collect_frt_errors(field: &str, frt: &str, context: &ValidationContext, errors: &mut Vec<ApiError>) {
+    let limits = &context.limits;
+    if frt.trim().is_empty() {
+        errors.push(ApiError::validation(field, "FRT is required"));
+        return;
+    }
+    let tree = match parse_frt(frt) {
+        Ok(tree) => tree,
+        Err(e) => {
+            errors.push(parse_error(field, e));
+            return;
+        }
+    };
+
+    let nodes = tree.injections.len() + tree.effects.len() + tree.branches.len();
+    if nodes > limits.max_entities {
+        errors.push(ApiError::validation(field, format!("FRT has {nodes} nodes (max {})", limits.max_entities)));
+    }
+    if tree.links.len() > limits.max_links {
+        errors.push(ApiError::validation(field, format!("FRT has {} links (max {})", tree.links.len(), limits.max_links)));
+    }
+    let texts = tree
+        .injections
+        .values()
+        .map(|injection| (format!("I{}", injection.id), &injection.text))
+        .chain(tree.effects.values().map(|effect| (format!("F{}", effect.id), &effect.text)))
+        .chain(tree.branches.values().map(|branch| (format!("N{}", branch.id), &branch.text)));
+    for (node, text) in texts {
+        let len = text.chars().count();
+        if len > limits.max_entity_text_len {
+            errors.push(ApiError::validation(
+                field,
+                format!("{node} text is {len} characters (max {})", limits.max_entity_text_len),
+            ));
+        }
+    }
+}
+
+fn check_unit_range(errors: &mut Vec<ApiError>, field: &str, label: &str, value: f32) {
+    if !(0.0..=1.0).contains(&value) {
+        errors.push(ApiError::validation(field, format!("{label} must be between 0 and 1")));
+    }
+}
+
+/// Check a real metric value converts into its catalogue unit and range.
+fn collect_metric_value_errors(catalogue: &MetricCatalogue, field: &str, name: &str, metric: &DoraMetric, errors: &mut Vec<ApiError>) {
+    let result = match catalogue.get(name) {
+        Some(config) => config.from_value(metric).map(|_| ()),
+        None => Err("Unknown metric".to_string()),
+    };
+    if let Err(message) = result {
+        errors.push(ApiError::validation(field, message));
+    }
+}
+
+/// Metrics with their own request fields, which the open `metrics` map must not repeat.
+pub const FIXED_METRICS: [&str; 7] = [
+    "deployment_frequency",
+    "lead_time",
+    "change_failure_rate",
+    "mttr",
+    "commit_frequency",
+    "branch_lifetime",
+    "pbis_delivered_per_sprint_per_team",
+];
+
+pub fn is_fixed_metric(name: &str) -> bool {
+    FIXED_METRICS.contains(&name)
+}
+
+fn collect_team_errors(field: &str, team: &str, errors: &mut Vec<ApiError>) {
+    if team.trim().is_empty() {
+        errors.push(ApiError::validation(field, "Team must not be empty"));
+    }
+}
+
+/// Check a history series names a catalogue metric in a convertible unit, with
+/// finite values and one observation per date.
+fn collect_series_errors(catalogue: &MetricCatalogue, field: &str, name: &str, series: &MetricSeries, errors: &mut Vec<ApiError>) {
+    let Some(config) = catalogue.get(name) else {
+        errors.push(ApiError::validation(field, "Unknown metric"));
+        return;
+    };
+    if convert_unit(1.0, &series.unit, &config.unit).is_none() {
+        errors.push(ApiError::validation(
+            format!("{field}.unit"),
+            format!("Cannot convert '{}' to '{}'", series.unit, config.unit),
+        ));
+    }
+    let mut dates = HashSet::new();
+    for (index, observation) in series.observations.iter().enumerate() {
+        let observation_field = format!("{field}.observations[{index}]");
+        if !observation.value.is_finite() {
+            errors.push(ApiError::validation(&observation_field, "Observation must be a finite number"));
+        }
+        if !dates.insert(observation.date) {
+            errors.push(ApiError::validation(
+                observation_field,
+                format!("Duplicate observation for {}", observation.date),
+            ));
+        }
+    }
 }
 
 impl Validate for AnalyseRequest {
-    fn validate(&self) -> Result<(), String> {
-        if self.crt.is_empty() {
-            return Err("CRT is required".to_string());
+    fn collect_errors(&self, context: &ValidationContext, errors: &mut Vec<ApiError>) {
+        collect_crt_errors("crt", &self.crt, context, errors);
+
+        match &self.dora_metrics {
+            DoraMetricsInput::Sliders(dora) => {
+                check_unit_range(errors, "dora_metrics.deployment_frequency", "Deployment frequency", dora.deployment_frequency);
+                check_unit_range(errors, "dora_metrics.lead_time", "Lead time", dora.lead_time);
+                check_unit_range(errors, "dora_metrics.change_failure_rate", "Change failure rate", dora.change_failure_rate);
+                check_unit_range(errors, "dora_metrics.mttr", "MTTR", dora.mttr);
+            }
+            DoraMetricsInput::Values(values) => {
+                for (name, metric) in values.iter() {
+                    collect_metric_value_errors(&context.catalogue, &format!("dora_metrics.{name}"), name, metric, errors);
+                }
+            }
+        }
+
+        let engineering = &self.extended_engineering_metrics;
+        check_unit_range(errors, "extended_engineering_metrics.commit_frequency", "Commit frequency", engineering.commit_frequency);
+        check_unit_range(errors, "extended_engineering_metrics.branch_lifetime", "Branch lifetime", engineering.branch_lifetime);
+        check_unit_range(
+            errors,
+            "extended_engineering_metrics.pbis_delivered_per_sprint_per_team",
+            "PBIs delivered per sprint per team",
+            engineering.pbis_delivered_per_sprint_per_team,
+        );
+
+        for (name, input) in &self.metrics {
+            let field = format!("metrics.{name}");
+            if is_fixed_metric(name) {
+                errors.push(ApiError::validation(
+                    field,
+                    "Report this metric under dora_metrics or extended_engineering_metrics",
+                ));
+                continue;
+            }
+            match input {
+                MetricInput::Slider(value) => {
+                    if context.catalogue.get(name).is_none() {
+                        errors.push(ApiError::validation(field, "Unknown metric"));
+                    } else {
+                        check_unit_range(errors, &field, name, *value);
+                    }
+                }
+                MetricInput::Value(metric) => collect_metric_value_errors(&context.catalogue, &field, name, metric, errors),
+            }
+        }
+
+        if let Some(score) = self.westrum {
+            if !westrum::is_valid_score(score) {
+                errors.push(ApiError::validation(
+                    "westrum",
+                    format!("Westrum must be between {WESTRUM_MIN} and {WESTRUM_MAX}"),
+                ));
+            }
         }
-        if self.dora_metrics.deployment_frequency < 0.0 || self.dora_metrics.deployment_frequency > 1.0 {
-            return Err("Deployment frequency must be between 0 and 1".to_string());
+        if let Some(survey) = &self.westrum_survey {
+            if survey.responses.is_empty() {
+                errors.push(ApiError::validation("westrum_survey.responses", "Westrum survey must have at least one response"));
+            }
+            for (respondent, response) in survey.responses.iter().enumerate() {
+                for (question, answer) in response.answers.iter().enumerate() {
+                    if !westrum::is_valid_score(*answer as f32) {
+                        errors.push(ApiError::validation(
+                            format!("westrum_survey.responses[{respondent}].answers[{question}]"),
+                            format!("Westrum answers must be between {WESTRUM_MIN} and {WESTRUM_MAX}"),
+                        ));
+                    }
+                }
+            }
         }
-        if self.dora_metrics.lead_time < 0.0 || self.dora_metrics.lead_time > 1.0 {
-            return Err("Lead time must be between 0 and 1".to_string());
+
+        if let Some(team) = &self.team {
+            collect_team_errors("team", team, errors);
         }
-        if self.dora_metrics.change_failure_rate < 0.0 || self.dora_metrics.change_failure_rate > 1.0 {
-            return Err("Change failure rate must be between 0 and 1".to_string());
+        for (name, series) in &self.history {
+            collect_series_errors(&context.catalogue, &format!("history.{name}"), name, series, errors);
         }
-        if self.dora_metrics.mttr < 0.0 || self.dora_metrics.mttr > 1.0 {
-            return Err("MTTR must be between 0 and 1".to_string());
+
+        if let Some(report) = &self.dora_benchmark {
+            if find_dora_benchmark(report).is_none() {
+                let known: Vec<&str> = DORA_BENCHMARKS.iter().map(|b| b.report).collect();
+                errors.push(ApiError::validation(
+                    "dora_benchmark",
+                    format!("Unknown DORA benchmark '{report}' (expected one of {})", known.join(", ")),
+                ));
+            }
         }
-        if self.extended_engineering_metrics.commit_frequency < 0.0 || self.extended_engineering_metrics.commit_frequency > 1.0 {
-            return Err("Commit frequency must be between 0 and 1".to_string());
+
+        let allocation = &self.time_allocation;
+        for (bucket, value) in allocation.buckets() {
+            if value < 0 {
+                errors.push(ApiError::validation(
+                    format!("time_allocation.{bucket}"),
+                    "Time allocation must not be negative",
+                ));
+            }
         }
-        if self.extended_engineering_metrics.branch_lifetime < 0.0 || self.extended_engineering_metrics.branch_lifetime > 1.0 {
-            return Err("Branch lifetime must be between 0 and 1".to_string());
+        if !allocation.is_total_valid() {
+            errors.push(ApiError::validation(
+                "time_allocation",
+                format!(
+                    "Time allocation must sum to 100 (±{}, got {})",
+                    TimeAllocation::TOLERANCE,
+                    allocation.total()
+                ),
+            ));
         }
-        if self.extended_engineering_metrics.pbis_delivered_per_sprint_per_team < 0.0 || self.extended_engineering_metrics.pbis_delivered_per_sprint_per_team > 1.0 {
-            return Err("PBIs delivered per sprint per team must be between 0 and 1".to_string());
+    }
+}
+
+impl Validate for MetricHistoryUpdate {
+    fn collect_errors(&self, context: &ValidationContext, errors: &mut Vec<ApiError>) {
+        if let Some(team) = &self.team {
+            collect_team_errors("team", team, errors);
         }
-        if self.westrum < 0.0 || self.westrum > 7.0 {
-            return Err("Westrum must be between 0 and 7".to_string());
+        if self.metrics.is_empty() {
+            errors.push(ApiError::validation("metrics", "At least one metric is required"));
         }
-        if self.time_allocation.meetings < 0 || self.time_allocation.unplanned < 0 || self.time_allocation.bugs < 0 || self.time_allocation.feature < 0 || self.time_allocation.tech_debt < 0 {
-            return Err("Time allocation must be greater than 0".to_string());
+        for (name, metric) in &self.metrics {
+            collect_metric_value_errors(&context.catalogue, &format!("metrics.{name}"), name, metric, errors);
         }
-        if self.time_allocation.meetings + self.time_allocation.unplanned + self.time_allocation.bugs + self.time_allocation.feature + self.time_allocation.tech_debt != 100 {
-            return Err("Time allocation must sum to 100".to_string());
-        } 
-        Ok(())
     }
 }
 
 impl Validate for RefineRequest {
-    fn validate(&self) -> Result<(), String> {
+    fn collect_errors(&self, _context: &ValidationContext, errors: &mut Vec<ApiError>) {
         if self.content.trim().is_empty() {
-            return Err("Content must not be empty".to_string());
+            errors.push(ApiError::validation("content", "Content must not be empty"));
         }
         if self.content.len() > 100_000 {
-            return Err("Content is too large (max 100,000 characters)".to_string());
+            errors.push(ApiError::validation("content", "Content is too large (max 100,000 characters)"));
         }
-        Ok(())
     }
 }
 
-impl Validate for EvaluateRequest {
-    fn validate(&self) -> Result<(), String> {
-        self.original_payload.validate()?;
-        
-        if self.analysis_result.executive_summary.is_empty() {
-            return Err("Analysis result must have an executive summary".to_string());
+impl Validate for CloudRequest {
+    fn collect_errors(&self, context: &ValidationContext, errors: &mut Vec<ApiError>) {
+        collect_crt_errors("crt", &self.crt, context, errors);
+        if let Ok(tree) = parse_crt(&self.crt) {
+            match self.root_cause {
+                Some(id) if !tree.entities.contains_key(&id) => {
+                    errors.push(ApiError::validation("root_cause", format!("Entity E{id} is not in the CRT")));
+                }
+                None if core_root_cause(&tree).is_none() => {
+                    errors.push(ApiError::validation("root_cause", "CRT has no root cause to build a cloud from"));
+                }
+                _ => {}
+            }
         }
-        if self.analysis_result.core_systemic_issues.is_empty() {
-            return Err("Analysis result must have at least one core systemic issue".to_string());
+        if let Some(cloud) = &self.cloud {
+            collect_cloud_errors("cloud", cloud, context, errors);
         }
-        if self.analysis_result.leverage_points.is_empty() {
-            return Err("Analysis result must have at least one leverage point".to_string());
+    }
+}
+
+impl Validate for FrtCheckRequest {
+    fn collect_errors(&self, context: &ValidationContext, errors: &mut Vec<ApiError>) {
+        collect_crt_errors("crt", &self.crt, context, errors);
+        collect_frt_errors("frt", &self.frt, context, errors);
+    }
+}
+
+impl Validate for NegateRequest {
+    fn collect_errors(&self, context: &ValidationContext, errors: &mut Vec<ApiError>) {
+        collect_crt_errors("crt", &self.crt, context, errors);
+        if self.injected.is_empty() {
+            errors.push(ApiError::validation("injected", "Choose at least one root cause to inject against"));
         }
-        if self.analysis_result.analysis_confidence.is_empty() {
-            return Err("Analysis result must have analysis confidence".to_string());
+        if let Ok(tree) = parse_crt(&self.crt) {
+            for id in self.injected.iter().filter(|id| !tree.entities.contains_key(id)) {
+                errors.push(ApiError::validation("injected", format!("Entity E{id} is not in the CRT")));
+            }
         }
-        Ok(())
+    }
+}
+
+fn collect_analysis_result_errors(result: &AnalysisResult, errors: &mut Vec<ApiError>) {
+    if result.executive_summary.is_empty() {
+        errors.push(ApiError::validation("analysis_result.executive_summary", "Analysis result must have an executive summary"));
+    }
+    if result.core_systemic_issues.is_empty() {
+        errors.push(ApiError::validation("analysis_result.core_systemic_issues", "Analysis result must have at least one core systemic issue"));
+    }
+    if result.leverage_points.is_empty() {
+        errors.push(ApiError::validation("analysis_result.leverage_points", "Analysis result must have at least one leverage point"));
+    }
+    if result.analysis_confidence.is_empty() {
+        errors.push(ApiError::validation("analysis_result.analysis_confidence", "Analysis result must have analysis confidence"));
+    }
+}
+
+fn collect_original_payload_errors(payload: &AnalyseRequest, context: &ValidationContext, errors: &mut Vec<ApiError>) {
+    let mut nested = Vec::new();
+    payload.collect_errors(context, &mut nested);
+    errors.extend(nested.into_iter().map(|e| e.within("original_payload")));
+}
+
+impl Validate for EvaluateRequest {
+    fn collect_errors(&self, context: &ValidationContext, errors: &mut Vec<ApiError>) {
+        collect_original_payload_errors(&self.original_payload, context, errors);
+        collect_analysis_result_errors(&self.analysis_result, errors);
+    }
+}
+
+impl Validate for PlanRequest {
+    fn collect_errors(&self, context: &ValidationContext, errors: &mut Vec<ApiError>) {
+        collect_original_payload_errors(&self.original_payload, context, errors);
+        collect_analysis_result_errors(&self.analysis_result, errors);
     }
 }
 
 impl Validate for AnalyseWithFeedbackRequest {
-    fn validate(&self) -> Result<(), String> {
-        self.original_payload.validate()?;
-        
-        if self.analysis_result.executive_summary.is_empty() {
-            return Err("Analysis result must have an executive summary".to_string());
-        }
-        if self.analysis_result.core_systemic_issues.is_empty() {
-            return Err("Analysis result must have at least one core systemic issue".to_string());
+    fn collect_errors(&self, context: &ValidationContext, errors: &mut Vec<ApiError>) {
+        collect_original_payload_errors(&self.original_payload, context, errors);
+        collect_analysis_result_errors(&self.analysis_result, errors);
+
+        if !self.evaluation.overall_assessment.total_score.is_finite() {
+            errors.push(ApiError::validation(
+                "evaluation.overall_assessment.total_score",
+                "Evaluation feedback must include a valid overall score",
+            ));
         }
-        if self.analysis_result.leverage_points.is_empty() {
-            return Err("Analysis result must have at least one leverage point".to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid_request() -> AnalyseRequest {
+        AnalyseRequest {
+            crt: "Entities\nE1. First\nE2. Second\n\nLinks\nL1. E1 → E2\n".to_string(),
+            dora_metrics: DoraMetricsInput::Sliders(DoraMetrics {
+                deployment_frequency: 0.5,
+                lead_time: 0.5,
+                change_failure_rate: 0.5,
+                mttr: 0.5,
+            }),
+            extended_engineering_metrics: EngineeringMetrics {
+                commit_frequency: 0.5,
+                branch_lifetime: 0.5,
+                pbis_delivered_per_sprint_per_team: 0.5,
+            },
+            westrum: Some(4.0),
+            westrum_survey: None,
+            time_allocation: TimeAllocation {
+                meetings: 20,
+                unplanned: 20,
+                bugs: 20,
+                feature: 20,
+                tech_debt: 20,
+            },
+            dora_benchmark: None,
+            metrics: Default::default(),
+            team: None,
+            history: Default::default(),
+            include_trends: false,
         }
-        if self.analysis_result.analysis_confidence.is_empty() {
-            return Err("Analysis result must have analysis confidence".to_string());
+    }
+
+    #[test]
+    fn rejects_unknown_dora_benchmark() {
+        let mut request = valid_request();
+        request.dora_benchmark = Some("1999".to_string());
+        let error = request.validate().unwrap_err();
+        assert_eq!(error.field(), Some("dora_benchmark"));
+
+        request.dora_benchmark = Some("2021".to_string());
+        assert!(request.validate().is_ok());
+    }
+
+    #[test]
+    fn valid_request_passes() {
+        assert!(valid_request().validate_all().is_ok());
+    }
+
+    #[test]
+    fn collects_every_violation() {
+        let mut request = valid_request();
+        request.crt = "Entities\nE1. First\n\nLinks\nL1. E1 → E9\n".to_string();
+        if let DoraMetricsInput::Sliders(dora) = &mut request.dora_metrics {
+            dora.lead_time = 1.5;
         }
-        
-        if !self.evaluation.overall_assessment.total_score.is_finite() {
-            return Err("Evaluation feedback must include a valid overall score".to_string());
+        request.extended_engineering_metrics.branch_lifetime = -0.1;
+        request.westrum = Some(9.0);
+        request.time_allocation.bugs = -5;
+
+        let mut errors = Vec::new();
+        request.collect_errors(&ValidationContext::default(), &mut errors);
+        let fields: Vec<_> = errors.iter().filter_map(|e| e.field()).collect();
+
+        assert_eq!(
+            fields,
+            vec![
+                "crt",
+                "dora_metrics.lead_time",
+                "extended_engineering_metrics.branch_lifetime",
+                "westrum",
+                "time_allocation.bugs",
+                "time_allocation",
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_crt_carries_diagnostics() {
+        let mut request = valid_request();
+        request.crt = "Entities\nE1. First\nE1. Again\n\nLinks\n".to_string();
+
+        let error = request.validate().unwrap_err();
+        assert_eq!(error.code(), "invalid_crt");
+        let details = error.details().unwrap();
+        assert_eq!(details["diagnostics"][0]["line"], 3);
+    }
+
+    #[test]
+    fn enforces_crt_limits() {
+        let context = ValidationContext {
+            limits: ValidationLimits {
+                max_entities: 1,
+                max_links: 0,
+                max_entity_text_len: 5,
+            },
+            ..Default::default()
+        };
+        let mut errors = Vec::new();
+        valid_request().collect_errors(&context, &mut errors);
+        let messages: Vec<_> = errors.iter().map(|e| e.message()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "CRT has 2 entities (max 1)",
+                "CRT has 1 links (max 0)",
+                "Entity E2 text is 6 characters (max 5)",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_out_of_range_survey_answers() {
+        let mut request = valid_request();
+        request.westrum_survey = Some(crate::westrum::WestrumSurvey {
+            responses: vec![
+                crate::westrum::WestrumAnswers { answers: [4, 4, 4, 4, 4, 4] },
+                crate::westrum::WestrumAnswers { answers: [4, 9, 4, 4, 4, 4] },
+            ],
+        });
+
+        let error = request.validate().unwrap_err();
+        assert_eq!(error.field(), Some("westrum_survey.responses[1].answers[1]"));
+    }
+
+    #[test]
+    fn names_the_dora_metric_that_fails_to_deserialise() {
+        let error = serde_json::from_str::<DoraMetricsInput>(
+            r#"{"deployment_frequency": 0.5, "lead_time": "fast", "change_failure_rate": 0.5, "mttr": 0.5}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().starts_with("lead_time: invalid type: string \"fast\""), "{error}");
+
+        let error = serde_json::from_str::<DoraMetricsInput>(
+            r#"{"deployment_frequency": {"value": 3, "unit": "deployments/week"}, "lead_time": {"value": 84}, "change_failure_rate": {"value": 12, "unit": "%"}, "mttr": {"value": 3, "unit": "days"}}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().starts_with("lead_time: missing field `unit`"), "{error}");
+
+        let error = serde_json::from_str::<DoraMetricsInput>(r#"{"deployment_frequency": 0.5, "lead_time": 0.5, "mttr": 0.5}"#).unwrap_err();
+        assert!(error.to_string().starts_with("missing field `change_failure_rate`"), "{error}");
+    }
+
+    #[test]
+    fn accepts_real_dora_values_within_config_range() {
+        let json = r#"{
+            "deployment_frequency": {"value": 3, "unit": "deployments/week"},
+            "lead_time": {"value": 84, "unit": "hours"},
+            "change_failure_rate": {"value": 12, "unit": "%"},
+            "mttr": {"value": 3, "unit": "weeks"}
+        }"#;
+        let mut request = valid_request();
+        request.dora_metrics = serde_json::from_str(json).unwrap();
+        assert!(matches!(request.dora_metrics, DoraMetricsInput::Values(_)));
+        let error = request.validate_all().unwrap_err();
+
+        // 3 weeks is beyond the 14 day MTTR range; everything else converts
+        let details = error.details().unwrap();
+        assert_eq!(details["errors"].as_array().unwrap().len(), 1);
+        assert_eq!(details["errors"][0]["field"], "dora_metrics.mttr");
+    }
+
+    #[test]
+    fn checks_open_metrics_against_catalogue() {
+        let catalogue = MetricCatalogue::from_json(
+            r#"{"metrics": [{"name": "rework_rate", "unit": "%", "min_value": 0, "max_value": 100, "inverted": true}]}"#,
+        )
+        .unwrap();
+        let context = ValidationContext {
+            catalogue: Arc::new(catalogue),
+            ..Default::default()
+        };
+        let mut request = valid_request();
+        request.metrics = serde_json::from_str(
+            r#"{
+                "rework_rate": {"value": 140, "unit": "%"},
+                "flow_efficiency": 0.4,
+                "lead_time": 0.5
+            }"#,
+        )
+        .unwrap();
+
+        let mut errors = Vec::new();
+        request.collect_errors(&context, &mut errors);
+        let fields: Vec<_> = errors.iter().filter_map(|e| e.field()).collect();
+        assert_eq!(fields, vec!["metrics.flow_efficiency", "metrics.lead_time", "metrics.rework_rate"]);
+
+        request.metrics = serde_json::from_str(r#"{"rework_rate": 0.3}"#).unwrap();
+        assert!(request.validate_all_with(&context).is_ok());
+        assert!(request.validate_all().is_err());
+    }
+
+    #[test]
+    fn validate_stops_at_first_violation() {
+        let mut request = valid_request();
+        if let DoraMetricsInput::Sliders(dora) = &mut request.dora_metrics {
+            dora.mttr = 2.0;
         }
-        Ok(())
+        request.westrum = Some(-1.0);
+
+        let error = request.validate().unwrap_err();
+        assert_eq!(error.field(), Some("dora_metrics.mttr"));
+    }
+
+    #[test]
+    fn time_allocation_allows_shared_tolerance() {
+        let mut request = valid_request();
+        request.time_allocation.feature = 19;
+        assert!(request.validate().is_ok());
+
+        request.time_allocation.feature = 18;
+        let error = request.validate().unwrap_err();
+        assert_eq!(error.message(), "Time allocation must sum to 100 (±1, got 98)");
+    }
+
+    #[test]
+    fn rejects_unknown_metric_tags() {
+        let mut request = valid_request();
+        request.crt = "Entities\nE1. First [metric: lead_time]\nE2. Second [metric: velocity]\n\nLinks\nL1. E1 → E2\n".to_string();
+        let error = request.validate().unwrap_err();
+        assert_eq!(error.message(), "Entity E2 is tagged with unknown metric 'velocity'");
+    }
+
+    #[test]
+    fn checks_cloud_requests() {
+        let mut request = CloudRequest {
+            crt: valid_request().crt,
+            root_cause: None,
+            cloud: Some("Cloud\nD. Release in large batches\n".to_string()),
+        };
+        assert!(request.validate_all().is_ok());
+
+        request.root_cause = Some(42);
+        request.cloud = Some("Cloud\nD. One\nD. Two\n".to_string());
+        let mut errors = Vec::new();
+        request.collect_errors(&ValidationContext::default(), &mut errors);
+        assert_eq!(errors[0].field(), Some("root_cause"));
+        assert_eq!(errors[0].message(), "Entity E42 is not in the CRT");
+        assert_eq!((errors[1].code(), errors[1].field()), ("invalid_crt", Some("cloud")));
+    }
+
+    #[test]
+    fn checks_frt_requests() {
+        let mut request = FrtCheckRequest {
+            crt: valid_request().crt,
+            frt: "Injections\nI1. Act\n\nEffects\nF1. Better\n\nLinks\nL1. I1 → F1\n".to_string(),
+        };
+        assert!(request.validate_all().is_ok());
+
+        let context = ValidationContext {
+            limits: ValidationLimits {
+                max_entities: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut errors = Vec::new();
+        request.collect_errors(&context, &mut errors);
+        assert_eq!(errors.last().unwrap().message(), "FRT has 2 nodes (max 1)");
+
+        request.frt = "Injections\n\nEffects\n\nLinks\nL1. I1 → F1\n".to_string();
+        let error = request.validate().unwrap_err();
+        assert_eq!((error.code(), error.field()), ("invalid_crt", Some("frt")));
+    }
+
+    #[test]
+    fn checks_negate_requests() {
+        let mut request = NegateRequest {
+            crt: valid_request().crt,
+            injected: vec![1],
+        };
+        assert!(request.validate_all().is_ok());
+
+        request.injected = vec![1, 7];
+        assert_eq!(request.validate().unwrap_err().message(), "Entity E7 is not in the CRT");
+
+        request.injected.clear();
+        let error = request.validate().unwrap_err();
+        assert_eq!(error.field(), Some("injected"));
+        assert_eq!(error.message(), "Choose at least one root cause to inject against");
+    }
+
+    #[test]
+    fn checks_history_series() {
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let mut request = valid_request();
+        let mut lead_time = MetricSeries::new("hours");
+        lead_time.observations = vec![
+            MetricObservation { date, value: 30.0 },
+            MetricObservation { date, value: f32::NAN },
+        ];
+        request.history.insert("lead_time".to_string(), lead_time);
+        request.history.insert("mttr".to_string(), MetricSeries::new("deployments/day"));
+        request.history.insert("velocity".to_string(), MetricSeries::new("points"));
+
+        let Err(ApiError::ValidationFailed { errors }) = request.validate_all() else {
+            panic!("expected validation errors");
+        };
+        let fields: Vec<Option<&str>> = errors.iter().map(ApiError::field).collect();
+        assert_eq!(
+            fields,
+            vec![
+                Some("history.lead_time.observations[1]"),
+                Some("history.lead_time.observations[1]"),
+                Some("history.mttr.unit"),
+                Some("history.velocity"),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_payload_errors_are_prefixed() {
+        let mut payload = valid_request();
+        payload.time_allocation.feature = 0;
+
+        let mut errors = Vec::new();
+        collect_original_payload_errors(&payload, &ValidationContext::default(), &mut errors);
+        assert_eq!(errors[0].field(), Some("original_payload.time_allocation"));
     }
 }
@@ -5,9 +5,19 @@ use js_sys::{Array, Object, Reflect};
 #[cfg(feature = "wasm")]
 use crate::types::*;
 #[cfg(feature = "wasm")]
-use crate::validation::Validate;
+use crate::validation::{Validate, ValidationContext};
+#[cfg(feature = "wasm")]
+use crate::catalogue::{MetricCatalogue, MetricDefinition};
+#[cfg(feature = "wasm")]
+use std::{cell::RefCell, sync::Arc};
 #[cfg(feature = "wasm")]
 use crate::dora::*;
+#[cfg(feature = "wasm")]
+use crate::evidence::build_evidence_map;
+#[cfg(feature = "wasm")]
+use crate::trend::compute_trends;
+#[cfg(feature = "wasm")]
+use crate::westrum::{self, WestrumAnswers, WestrumCulture, WestrumSurvey, WESTRUM_QUESTIONS};
 
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
@@ -19,6 +29,7 @@ pub struct WasmAnalyseRequest {
 #[wasm_bindgen]
 impl WasmAnalyseRequest {
     #[wasm_bindgen(constructor)]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         crt: String,
         deployment_frequency: f32,
@@ -38,18 +49,19 @@ impl WasmAnalyseRequest {
         WasmAnalyseRequest {
             inner: AnalyseRequest {
                 crt,
-                dora_metrics: DoraMetrics {
+                dora_metrics: DoraMetricsInput::Sliders(DoraMetrics {
                     deployment_frequency,
                     lead_time,
                     change_failure_rate,
                     mttr,
-                },
+                }),
                 extended_engineering_metrics: EngineeringMetrics {
                     commit_frequency,
                     branch_lifetime,
                     pbis_delivered_per_sprint_per_team,
                 },
-                westrum: westrum.unwrap_or(0.0),
+                westrum,
+                westrum_survey: None,
                 time_allocation: TimeAllocation {
                     meetings,
                     unplanned,
@@ -57,13 +69,72 @@ impl WasmAnalyseRequest {
                     feature,
                     tech_debt,
                 },
+                dora_benchmark: None,
+                metrics: Default::default(),
+                team: None,
+                history: Default::default(),
+                include_trends: false,
             },
         }
     }
 
+    /// Validate the request, rejecting with an `ApiError` body that lists every
+    /// violation under `details.errors`.
     #[wasm_bindgen]
     pub fn validate(&self) -> Result<(), JsValue> {
-        self.inner.validate().map_err(|e| JsValue::from_str(&e))
+        let context = ValidationContext {
+            catalogue: metric_catalogue(),
+            ..Default::default()
+        };
+        self.inner
+            .validate_all_with(&context)
+            .map_err(|e| {
+                serde::Serialize::serialize(&e, &serde_wasm_bindgen::Serializer::json_compatible())
+                    .unwrap_or_else(|_| JsValue::from_str(&e.to_string()))
+            })
+    }
+
+    /// Attach a Westrum survey submission; its mean replaces the single score
+    /// when the request is sent to the agents.
+    #[wasm_bindgen]
+    pub fn set_westrum_survey_json(&mut self, json_str: &str) -> Result<(), JsValue> {
+        let survey: WestrumSurvey = serde_json::from_str(json_str).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.inner.westrum_survey = Some(survey);
+        Ok(())
+    }
+
+    /// Attach catalogue metrics as a JSON map of name to slider position or
+    /// `{value, unit}`.
+    #[wasm_bindgen]
+    pub fn set_metrics_json(&mut self, json_str: &str) -> Result<(), JsValue> {
+        self.inner.metrics = serde_json::from_str(json_str).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(())
+    }
+
+    /// Select the DORA report whose thresholds are used to tier the metrics.
+    #[wasm_bindgen]
+    pub fn set_dora_benchmark(&mut self, report: &str) {
+        self.inner.dora_benchmark = Some(report.to_string());
+    }
+
+    /// Name the team whose stored metric history the backend uses.
+    #[wasm_bindgen]
+    pub fn set_team(&mut self, team: &str) {
+        self.inner.team = Some(team.to_string());
+    }
+
+    /// Attach earlier observations as a JSON map of metric name to
+    /// `{unit, observations: [{date, value}]}`.
+    #[wasm_bindgen]
+    pub fn set_history_json(&mut self, json_str: &str) -> Result<(), JsValue> {
+        self.inner.history = serde_json::from_str(json_str).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(())
+    }
+
+    /// Ask the backend to send trend summaries to the agents.
+    #[wasm_bindgen]
+    pub fn set_include_trends(&mut self, include_trends: bool) {
+        self.inner.include_trends = include_trends;
     }
 
     #[wasm_bindgen]
@@ -72,14 +143,62 @@ impl WasmAnalyseRequest {
     }
 }
 
+#[cfg(feature = "wasm")]
+thread_local! {
+    static METRIC_CATALOGUE: RefCell<Arc<MetricCatalogue>> = RefCell::new(Arc::new(MetricCatalogue::builtin()));
+}
+
+#[cfg(feature = "wasm")]
+fn metric_catalogue() -> Arc<MetricCatalogue> {
+    METRIC_CATALOGUE.with(|catalogue| catalogue.borrow().clone())
+}
+
+#[cfg(feature = "wasm")]
+fn metric_definition(metric_name: &str) -> Result<MetricDefinition, JsValue> {
+    metric_catalogue()
+        .definition(metric_name)
+        .cloned()
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown metric: {}", metric_name)))
+}
+
+#[cfg(feature = "wasm")]
+fn metric_config(metric_name: &str) -> Result<DoraMetricConfig, JsValue> {
+    metric_definition(metric_name).map(|definition| definition.config)
+}
+
+#[cfg(feature = "wasm")]
+fn metric_config_info(definition: &MetricDefinition) -> serde_json::Value {
+    let config = &definition.config;
+    serde_json::json!({
+        "min_value": config.min_value,
+        "max_value": config.max_value,
+        "unit": config.unit,
+        "inverted": config.inverted,
+        "scale": config.scale,
+        "group": definition.group,
+    })
+}
+
+/// Replace the active metric catalogue with one parsed from JSON. Metrics not
+/// listed keep their built-in configs.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn set_metric_catalogue(json_str: &str) -> Result<(), JsValue> {
+    let catalogue = MetricCatalogue::from_json(json_str).map_err(|e| JsValue::from_str(&format!("{:#}", e)))?;
+    METRIC_CATALOGUE.with(|current| *current.borrow_mut() = Arc::new(catalogue));
+    Ok(())
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn get_metric_catalogue() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&*metric_catalogue())?)
+}
+
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn translate_dora_metric(metric_name: &str, slider_value: f32) -> Result<JsValue, JsValue> {
-    let config = DORA_METRIC_CONFIGS
-        .iter()
-        .find(|(name, _)| *name == metric_name)
-        .map(|(_, config)| config)
-        .ok_or_else(|| JsValue::from_str(&format!("Unknown metric: {}", metric_name)))?;
+    let config = metric_config(metric_name)?;
 
     let result = config.translate(slider_value);
     Ok(serde_wasm_bindgen::to_value(&result)?)
@@ -87,21 +206,19 @@ pub fn translate_dora_metric(metric_name: &str, slider_value: f32) -> Result<JsV
 
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
-pub fn get_dora_metric_config(metric_name: &str) -> Result<JsValue, JsValue> {
-    let config = DORA_METRIC_CONFIGS
-        .iter()
-        .find(|(name, _)| *name == metric_name)
-        .map(|(_, config)| config)
-        .ok_or_else(|| JsValue::from_str(&format!("Unknown metric: {}", metric_name)))?;
+pub fn dora_value_to_slider(metric_name: &str, value: f32, unit: &str) -> Result<f32, JsValue> {
+    let config = metric_config(metric_name)?;
 
-    let config_info = serde_json::json!({
-        "min_value": config.min_value,
-        "max_value": config.max_value,
-        "unit": config.unit,
-        "inverted": config.inverted,
-    });
+    let value = convert_unit(value, unit, &config.unit)
+        .ok_or_else(|| JsValue::from_str(&format!("Cannot convert '{}' to '{}'", unit, config.unit)))?;
+    Ok(config.to_slider(value))
+}
 
-    Ok(serde_wasm_bindgen::to_value(&config_info)?)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn get_dora_metric_config(metric_name: &str) -> Result<JsValue, JsValue> {
+    let definition = metric_definition(metric_name)?;
+    Ok(serde_wasm_bindgen::to_value(&metric_config_info(&definition))?)
 }
 
 #[cfg(feature = "wasm")]
@@ -109,7 +226,11 @@ pub fn get_dora_metric_config(metric_name: &str) -> Result<JsValue, JsValue> {
 pub fn validate_analyse_request_json(json_str: &str) -> Result<bool, JsValue> {
     match serde_json::from_str::<AnalyseRequest>(json_str) {
         Ok(request) => {
-            match request.validate() {
+            let context = ValidationContext {
+                catalogue: metric_catalogue(),
+                ..Default::default()
+            };
+            match request.validate_all_with(&context) {
                 Ok(_) => Ok(true),
                 Err(_) => Ok(false),
             }
@@ -121,17 +242,10 @@ pub fn validate_analyse_request_json(json_str: &str) -> Result<bool, JsValue> {
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn get_all_dora_metric_configs() -> Result<JsValue, JsValue> {
-    let configs: std::collections::HashMap<String, serde_json::Value> = DORA_METRIC_CONFIGS
+    let configs: std::collections::HashMap<String, serde_json::Value> = metric_catalogue()
+        .metrics
         .iter()
-        .map(|(name, config)| {
-            let config_info = serde_json::json!({
-                "min_value": config.min_value,
-                "max_value": config.max_value,
-                "unit": config.unit,
-                "inverted": config.inverted,
-            });
-            (name.to_string(), config_info)
-        })
+        .map(|definition| (definition.name.clone(), metric_config_info(definition)))
         .collect();
     
     Ok(serde_wasm_bindgen::to_value(&configs)?)
@@ -145,7 +259,7 @@ pub fn clamp_percentage(value: f32) -> i32 {
     if value.is_nan() {
         return 0;
     }
-    value.max(0.0).min(100.0).round() as i32
+    value.clamp(0.0, 100.0).round() as i32
 }
 
 #[cfg(feature = "wasm")]
@@ -160,8 +274,177 @@ pub fn to_int_percentage(value: f32) -> i32 {
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn is_time_allocation_valid(meetings: i32, unplanned: i32, bugs: i32, feature: i32, tech_debt: i32) -> bool {
-    let total = meetings + unplanned + bugs + feature + tech_debt;
-    (total - 100).abs() <= 1 // Allow 1% tolerance
+    TimeAllocation {
+        meetings,
+        unplanned,
+        bugs,
+        feature,
+        tech_debt,
+    }
+    .is_total_valid()
+}
+
+/// Whole percentages proportional to the given weights (e.g. hours), summing
+/// to exactly 100.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn normalise_time_allocation(meetings: f32, unplanned: f32, bugs: f32, feature: f32, tech_debt: f32) -> Result<JsValue, JsValue> {
+    let allocation = TimeAllocation::from_weights([meetings, unplanned, bugs, feature, tech_debt])
+        .ok_or_else(|| JsValue::from_str("At least one bucket needs a positive weight"))?;
+    Ok(serde_wasm_bindgen::to_value(&allocation)?)
+}
+
+/// Trend summaries for a JSON map of metric name to `{unit, observations}`,
+/// read against the active catalogue.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn compute_metric_trends(history_json: &str) -> Result<JsValue, JsValue> {
+    let history: MetricHistory = serde_json::from_str(history_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(serde_wasm_bindgen::to_value(&compute_trends(&metric_catalogue(), &history))?)
+}
+
+/// Evidence for the metric-tagged entities of an analyse request's CRT, keyed
+/// by entity id, as the analyser receives it.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn compute_evidence_map(request_json: &str) -> Result<JsValue, JsValue> {
+    let request: AnalyseRequest = serde_json::from_str(request_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let crt = crate::parser::parse_crt(&request.crt).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let catalogue = metric_catalogue();
+    let mut metrics = translate_dora_metrics_for_agent(&catalogue, &request.dora_metrics);
+    let tiers = classify_dora_metrics(&metrics, resolve_dora_benchmark(request.dora_benchmark.as_deref()));
+    metrics.extend(translate_engineering_metrics_for_agent(&catalogue, &request.extended_engineering_metrics));
+    metrics.extend(translate_metrics_for_agent(&catalogue, &request.metrics));
+    // keyed by id, so serialize as a plain object rather than a JS Map
+    let evidence = build_evidence_map(&crt, &metrics, &tiers, &catalogue);
+    Ok(serde::Serialize::serialize(&evidence, &serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// Parse an Evaporating Cloud document into `{boxes, assumptions}`, keyed by
+/// box (`"A"` … `"D'"`) and arrow (`"A-B"` … `"D-D'"`).
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_cloud_content(content: &str) -> Result<JsValue, JsValue> {
+    let cloud = crate::cloud::parse_cloud(content).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(serde::Serialize::serialize(&cloud, &serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// Parse a Future Reality Tree document into its injections, effects,
+/// negative branches and links, each keyed by id.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_frt_content(content: &str) -> Result<JsValue, JsValue> {
+    let frt = crate::frt::parse_frt(content).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(serde::Serialize::serialize(&frt, &serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// Check a Future Reality Tree against its CRT: which UDEs it answers, and
+/// which effects, branches and references are left dangling.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn check_future_reality_tree(frt_content: &str, crt_content: &str) -> Result<JsValue, JsValue> {
+    let frt = crate::frt::parse_frt(frt_content).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let crt = crate::parser::parse_crt(crt_content).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let check = crate::frt::check_frt(&frt, &crt);
+    Ok(serde::Serialize::serialize(&check, &serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// Trace how any of `from` leads to any of `to`: the simple paths (within
+/// the optional bounds), the shortest one, and the steps and links on any.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn trace_paths(
+    crt_content: &str,
+    from: Vec<u32>,
+    to: Vec<u32>,
+    max_paths: Option<usize>,
+    max_steps: Option<usize>,
+    max_visits: Option<usize>,
+) -> Result<JsValue, JsValue> {
+    let crt = crate::parser::parse_crt(crt_content).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let defaults = crate::paths::PathLimits::default();
+    let limits = crate::paths::PathLimits {
+        max_paths: max_paths.unwrap_or(defaults.max_paths),
+        max_steps: max_steps.unwrap_or(defaults.max_steps),
+        max_visits: max_visits.unwrap_or(defaults.max_visits),
+    };
+    let query = crate::paths::query_paths(&crt, &from, &to, limits).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(serde::Serialize::serialize(&query, &serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// The cheapest minimal sets of entities whose removal stops `target`
+/// holding, with an optional bound on their size.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn cut_sets(crt_content: &str, target: u32, max_size: Option<usize>) -> Result<JsValue, JsValue> {
+    let crt = crate::parser::parse_crt(crt_content).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let defaults = crate::cutsets::CutSetLimits::default();
+    let limits = crate::cutsets::CutSetLimits {
+        max_size: max_size.unwrap_or(defaults.max_size),
+        ..defaults
+    };
+    let cut_sets = crate::cutsets::minimal_cut_sets(&crt, target, limits).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(serde::Serialize::serialize(&cut_sets, &serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// Run a what-if over a CRT. `assignments_json` maps entity ids to `true` or
+/// `false`, e.g. `{"23": false}` for E23 fixed.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn simulate_crt(crt_content: &str, assignments_json: &str) -> Result<JsValue, JsValue> {
+    let crt = crate::parser::parse_crt(crt_content).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let assignments = serde_json::from_str(assignments_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let simulation = crate::simulation::simulate(&crt, &assignments).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(serde::Serialize::serialize(&simulation, &serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// Negate a CRT from the injected entities into a Future Reality Tree
+/// skeleton, with the UDEs it neutralises and those that remain.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn negate_crt_content(crt_content: &str, injected: Vec<u32>) -> Result<JsValue, JsValue> {
+    let crt = crate::parser::parse_crt(crt_content).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let skeleton = crate::negation::negate_crt(&crt, &injected).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let response = NegateResponse {
+        document: skeleton.frt.to_string(),
+        skeleton,
+    };
+    Ok(serde::Serialize::serialize(&response, &serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// Export a cloud in the shape `parse_cloud_content` returns as a cloud document.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn export_cloud(cloud_json: &str) -> Result<String, JsValue> {
+    let cloud: crate::cloud::EvaporatingCloud =
+        serde_json::from_str(cloud_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(cloud.to_string())
+}
+
+/// Parse a Prerequisite Tree document into its goal, obstacles and
+/// intermediate objectives, each keyed by id.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_prt_content(content: &str) -> Result<JsValue, JsValue> {
+    let prt = crate::prt::parse_prt(content).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(serde::Serialize::serialize(&prt, &serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// Parse a Transition Tree document into its objective and steps keyed by id.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_transition_content(content: &str) -> Result<JsValue, JsValue> {
+    let tree = crate::transition::parse_transition_tree(content).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(serde::Serialize::serialize(&tree, &serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// Draft a Prerequisite Tree document from the planner agent's JSON output.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn prt_from_planner_output(planner_json: &str) -> Result<String, JsValue> {
+    let plan: PlannerOutput = serde_json::from_str(planner_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let tree = crate::prt::PrerequisiteTree::from_plan(&plan).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(tree.to_string())
 }
 
 #[cfg(feature = "wasm")]
@@ -175,11 +458,12 @@ pub fn is_dora_complete(deployment_frequency: Option<f32>, lead_time: Option<f32
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn is_westrum_provided(westrum_score: Option<f32>) -> bool {
-    westrum_score.is_some() && westrum_score.unwrap().is_finite()
+    westrum_score.is_some_and(westrum::is_valid_score)
 }
 
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
 pub fn is_analysis_ready(crt_content: &str, deployment_frequency: Option<f32>, 
                         lead_time: Option<f32>, change_failure_rate: Option<f32>, 
                         mttr: Option<f32>, meetings: i32, unplanned: i32, 
@@ -211,21 +495,62 @@ pub fn is_analysis_ready(crt_content: &str, deployment_frequency: Option<f32>,
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn get_westrum_descriptor(score: f32) -> String {
-    if !score.is_finite() {
-        return "Unknown".to_string();
-    }
-    
-    let rounded = score.round() as i32;
-    match rounded {
-        1 => "Pathological".to_string(),
-        2 => "Bureaucratic".to_string(), 
-        3 => "Generative".to_string(),
-        4 => "Performance".to_string(),
-        5 => "Learning".to_string(),
-        _ => "Unknown".to_string(),
-    }
+    WestrumCulture::from_score(score)
+        .map(|culture| culture.label())
+        .unwrap_or("Unknown")
+        .to_string()
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn get_westrum_questions() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&WESTRUM_QUESTIONS)?)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn aggregate_westrum_survey(json_str: &str) -> Result<JsValue, JsValue> {
+    let survey: WestrumSurvey = serde_json::from_str(json_str).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let summary = survey
+        .aggregate()
+        .ok_or_else(|| JsValue::from_str("Westrum survey has no responses"))?;
+    Ok(serde_wasm_bindgen::to_value(&summary)?)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn westrum_score_from_answers(answers: Vec<u8>) -> Result<f32, JsValue> {
+    let answers: [u8; 6] = answers
+        .try_into()
+        .map_err(|_| JsValue::from_str("Expected 6 Westrum answers"))?;
+    let answers = WestrumAnswers::new(answers).map_err(|e| JsValue::from_str(&e))?;
+    Ok(answers.score())
 }
 
+/// Tier of a metric value in its configured unit, against the given benchmark
+/// report (defaults to `DEFAULT_DORA_BENCHMARK`).
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn classify_dora_metric(metric_name: &str, value: f32, report: Option<String>) -> Result<String, JsValue> {
+    let report = report.as_deref().unwrap_or(DEFAULT_DORA_BENCHMARK);
+    let benchmark = find_dora_benchmark(report)
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown DORA benchmark: {}", report)))?;
+    let tier = benchmark
+        .classify(metric_name, value)
+        .ok_or_else(|| JsValue::from_str(&format!("No benchmark for metric: {}", metric_name)))?;
+    Ok(tier.label().to_string())
+}
+
+/// Tier of a slider position, translated to the metric's real value first.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn classify_dora_slider(metric_name: &str, slider_value: f32, report: Option<String>) -> Result<String, JsValue> {
+    let config = metric_config(metric_name)?;
+    classify_dora_metric(metric_name, config.translate(slider_value).value, report)
+}
+
+/// Quartile of a raw slider position. This ignores the metric and its
+/// direction; prefer `classify_dora_slider` for benchmark tiers.
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn to_dora_category_name(value: f32) -> String {
@@ -250,11 +575,11 @@ pub fn validate_refine_response_json(json_str: &str) -> Result<bool, JsValue> {
             let has_entities = response.get("CRT Restatement")
                 .and_then(|r| r.get("Entities"))
                 .and_then(|e| e.as_array())
-                .map_or(false, |arr| !arr.is_empty());
+                .is_some_and(|arr| !arr.is_empty());
             let has_links = response.get("CRT Restatement")
                 .and_then(|r| r.get("Links"))
                 .and_then(|l| l.as_array())
-                .map_or(false, |arr| !arr.is_empty());
+                .is_some_and(|arr| !arr.is_empty());
             
             Ok(has_restatement && has_entities && has_links)
         }
@@ -351,6 +676,10 @@ pub fn parse_content(content: &str) -> Result<JsValue, JsValue> {
             &JsValue::from_str("type"),
             &JsValue::from_str("normal"),
         )?;
+        let metrics = entity.metrics.iter().map(|metric| JsValue::from_str(metric)).collect::<Array>();
+        Reflect::set(&node_obj, &JsValue::from_str("metrics"), &metrics)?;
+        let attributes = serde::Serialize::serialize(&entity.attributes, &serde_wasm_bindgen::Serializer::json_compatible())?;
+        Reflect::set(&node_obj, &JsValue::from_str("attributes"), &attributes)?;
         nodes_array.push(&node_obj);
     }
 
@@ -392,7 +721,7 @@ pub fn parse_content(content: &str) -> Result<JsValue, JsValue> {
                 let source_id = JsValue::from_f64(source.id as f64);
                 for target in &to_terms {
                     let target_id = JsValue::from_f64(target.id as f64);
-                    push_link(
+                    let link_obj = push_link(
                         &links_array,
                         source_id.clone(),
                         target_id,
@@ -400,6 +729,15 @@ pub fn parse_content(content: &str) -> Result<JsValue, JsValue> {
                         source.negated,
                         target.negated,
                     )?;
+                    Reflect::set(&link_obj, &JsValue::from_str("link"), &JsValue::from_f64(link.id as f64))?;
+                    // left out when unstated, so the view can tell it from a stated 1
+                    if let Some(confidence) = link.confidence {
+                        let confidence = JsValue::from_f64(confidence as f64);
+                        Reflect::set(&link_obj, &JsValue::from_str("confidence"), &confidence)?;
+                    }
+                    if let Some(evidence) = &link.evidence {
+                        Reflect::set(&link_obj, &JsValue::from_str("evidence"), &JsValue::from_str(evidence))?;
+                    }
                 }
             }
         }
@@ -464,7 +802,7 @@ fn push_link(
     relation_type: &str,
     source_negated: bool,
     target_negated: bool,
-) -> Result<(), JsValue> {
+) -> Result<Object, JsValue> {
     let link_obj = Object::new();
     Reflect::set(&link_obj, &JsValue::from_str("source"), &source)?;
     Reflect::set(&link_obj, &JsValue::from_str("target"), &target)?;
@@ -484,7 +822,7 @@ fn push_link(
         &JsValue::from_bool(target_negated),
     )?;
     links_array.push(&link_obj);
-    Ok(())
+    Ok(link_obj)
 }
 
 // Utility function to get node count
@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+
+/// Lowest answer on the 7-point Likert scale, and the lowest culture score.
+pub const WESTRUM_MIN: f32 = 1.0;
+/// Highest answer on the 7-point Likert scale, and the highest culture score.
+pub const WESTRUM_MAX: f32 = 7.0;
+
+/// The six statements of the Westrum organizational culture survey, each
+/// answered from 1 (strongly disagree) to 7 (strongly agree).
+pub const WESTRUM_QUESTIONS: [&str; 6] = [
+    "On my team, information is actively sought.",
+    "On my team, failures are learning opportunities, and messengers of them are not punished.",
+    "On my team, responsibilities are shared.",
+    "On my team, cross-functional collaboration is encouraged and rewarded.",
+    "On my team, failure causes enquiry.",
+    "On my team, new ideas are welcomed.",
+];
+
+/// Returns true when `score` is finite and within `WESTRUM_MIN..=WESTRUM_MAX`.
+pub fn is_valid_score(score: f32) -> bool {
+    score.is_finite() && (WESTRUM_MIN..=WESTRUM_MAX).contains(&score)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WestrumCulture {
+    Pathological,
+    Bureaucratic,
+    Generative,
+}
+
+impl WestrumCulture {
+    /// Classify a culture score. The 1–7 range is split into thirds:
+    /// below 3 is pathological, below 5 bureaucratic, and 5 or above generative.
+    pub fn from_score(score: f32) -> Option<Self> {
+        if !is_valid_score(score) {
+            return None;
+        }
+        Some(if score < 3.0 {
+            WestrumCulture::Pathological
+        } else if score < 5.0 {
+            WestrumCulture::Bureaucratic
+        } else {
+            WestrumCulture::Generative
+        })
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            WestrumCulture::Pathological => "Pathological",
+            WestrumCulture::Bureaucratic => "Bureaucratic",
+            WestrumCulture::Generative => "Generative",
+        }
+    }
+}
+
+/// One respondent's answers to the six Westrum questions, in
+/// `WESTRUM_QUESTIONS` order.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WestrumAnswers {
+    pub answers: [u8; 6],
+}
+
+impl WestrumAnswers {
+    pub fn new(answers: [u8; 6]) -> Result<Self, String> {
+        for (index, answer) in answers.iter().enumerate() {
+            if !is_valid_score(*answer as f32) {
+                return Err(format!(
+                    "Answer to question {} must be between {} and {}",
+                    index + 1,
+                    WESTRUM_MIN,
+                    WESTRUM_MAX
+                ));
+            }
+        }
+        Ok(WestrumAnswers { answers })
+    }
+
+    /// The culture score: the mean of the six answers.
+    pub fn score(&self) -> f32 {
+        self.answers.iter().map(|a| *a as f32).sum::<f32>() / self.answers.len() as f32
+    }
+
+    pub fn culture(&self) -> Option<WestrumCulture> {
+        WestrumCulture::from_score(self.score())
+    }
+}
+
+/// Fewer responses than this produce a low-response warning.
+pub const WESTRUM_MIN_RESPONDENTS: usize = 5;
+
+/// A team's survey submission: one set of answers per respondent.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WestrumSurvey {
+    pub responses: Vec<WestrumAnswers>,
+}
+
+/// How closely respondents agree, judged by the standard deviation of their
+/// culture scores: below 1.0 consistent, below 1.5 mixed, otherwise divided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WestrumAgreement {
+    Consistent,
+    Mixed,
+    Divided,
+}
+
+impl WestrumAgreement {
+    pub fn from_std_dev(std_dev: f32) -> Self {
+        if std_dev < 1.0 {
+            WestrumAgreement::Consistent
+        } else if std_dev < 1.5 {
+            WestrumAgreement::Mixed
+        } else {
+            WestrumAgreement::Divided
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WestrumQuestionSummary {
+    pub question: String,
+    pub mean: f32,
+    pub median: f32,
+    pub std_dev: f32,
+    /// Number of respondents choosing each answer, index 0 being answer 1.
+    pub counts: [u32; 7],
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WestrumSummary {
+    pub respondents: usize,
+    pub mean: f32,
+    pub median: f32,
+    pub std_dev: f32,
+    pub min: f32,
+    pub max: f32,
+    pub culture: Option<WestrumCulture>,
+    pub agreement: WestrumAgreement,
+    pub per_question: Vec<WestrumQuestionSummary>,
+    pub warnings: Vec<String>,
+}
+
+impl WestrumSurvey {
+    /// Aggregate the responses, or `None` when there are none.
+    pub fn aggregate(&self) -> Option<WestrumSummary> {
+        if self.responses.is_empty() {
+            return None;
+        }
+
+        let scores: Vec<f32> = self.responses.iter().map(WestrumAnswers::score).collect();
+        let (mean, median, std_dev) = describe(&scores);
+        let min = scores.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let agreement = WestrumAgreement::from_std_dev(std_dev);
+
+        let mut warnings = Vec::new();
+        if scores.len() < WESTRUM_MIN_RESPONDENTS {
+            warnings.push(format!(
+                "Only {} response(s); at least {} are needed for a representative score",
+                scores.len(),
+                WESTRUM_MIN_RESPONDENTS
+            ));
+        }
+        if agreement == WestrumAgreement::Divided {
+            warnings.push(format!(
+                "Respondents are divided (standard deviation {std_dev:.2}); the mean hides distinct sub-cultures"
+            ));
+        }
+
+        let per_question = WESTRUM_QUESTIONS
+            .iter()
+            .enumerate()
+            .map(|(index, question)| {
+                let answers: Vec<f32> = self.responses.iter().map(|r| r.answers[index] as f32).collect();
+                let (mean, median, std_dev) = describe(&answers);
+                let mut counts = [0u32; 7];
+                for response in &self.responses {
+                    if let Some(count) = counts.get_mut((response.answers[index] as usize).wrapping_sub(1)) {
+                        *count += 1;
+                    }
+                }
+                WestrumQuestionSummary {
+                    question: question.to_string(),
+                    mean,
+                    median,
+                    std_dev,
+                    counts,
+                }
+            })
+            .collect();
+
+        Some(WestrumSummary {
+            respondents: scores.len(),
+            mean,
+            median,
+            std_dev,
+            min,
+            max,
+            culture: WestrumCulture::from_score(mean),
+            agreement,
+            per_question,
+            warnings,
+        })
+    }
+}
+
+/// The score sent to the agents: the survey mean when a survey was submitted,
+/// otherwise the single score the team provided.
+pub fn effective_score(score: Option<f32>, survey: Option<&WestrumSummary>) -> Option<f32> {
+    survey.map(|summary| summary.mean).or(score)
+}
+
+/// Mean, median and population standard deviation of a non-empty sample.
+fn describe(values: &[f32]) -> (f32, f32, f32) {
+    let n = values.len() as f32;
+    let mean = values.iter().sum::<f32>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n;
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+    let median = if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    };
+
+    (mean, median, variance.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_and_classifies_answers() {
+        let answers = WestrumAnswers::new([6, 7, 5, 6, 6, 6]).unwrap();
+        assert_eq!(answers.score(), 6.0);
+        assert_eq!(answers.culture(), Some(WestrumCulture::Generative));
+
+        let answers = WestrumAnswers::new([1, 2, 3, 2, 1, 3]).unwrap();
+        assert_eq!(answers.score(), 2.0);
+        assert_eq!(answers.culture(), Some(WestrumCulture::Pathological));
+    }
+
+    #[test]
+    fn rejects_answers_outside_likert_scale() {
+        assert!(WestrumAnswers::new([4, 4, 0, 4, 4, 4]).is_err());
+        assert!(WestrumAnswers::new([4, 4, 4, 4, 4, 8]).is_err());
+    }
+
+    #[test]
+    fn classification_boundaries() {
+        assert_eq!(WestrumCulture::from_score(2.99), Some(WestrumCulture::Pathological));
+        assert_eq!(WestrumCulture::from_score(3.0), Some(WestrumCulture::Bureaucratic));
+        assert_eq!(WestrumCulture::from_score(5.0), Some(WestrumCulture::Generative));
+        assert_eq!(WestrumCulture::from_score(0.5), None);
+        assert_eq!(WestrumCulture::from_score(f32::NAN), None);
+    }
+
+    #[test]
+    fn aggregates_consistent_survey() {
+        let survey = WestrumSurvey {
+            responses: vec![
+                WestrumAnswers { answers: [4, 4, 4, 4, 4, 4] },
+                WestrumAnswers { answers: [4, 4, 4, 4, 4, 4] },
+                WestrumAnswers { answers: [3, 3, 3, 3, 3, 3] },
+                WestrumAnswers { answers: [5, 5, 5, 5, 5, 5] },
+                WestrumAnswers { answers: [4, 4, 4, 4, 4, 4] },
+            ],
+        };
+        let summary = survey.aggregate().unwrap();
+
+        assert_eq!(summary.respondents, 5);
+        assert_eq!(summary.mean, 4.0);
+        assert_eq!(summary.median, 4.0);
+        assert_eq!(summary.culture, Some(WestrumCulture::Bureaucratic));
+        assert_eq!(summary.agreement, WestrumAgreement::Consistent);
+        assert_eq!(summary.per_question[0].counts, [0, 0, 1, 3, 1, 0, 0]);
+        assert!(summary.warnings.is_empty());
+    }
+
+    #[test]
+    fn flags_divided_and_low_response_surveys() {
+        let survey = WestrumSurvey {
+            responses: vec![
+                WestrumAnswers { answers: [1, 1, 1, 1, 1, 1] },
+                WestrumAnswers { answers: [7, 7, 7, 7, 7, 7] },
+            ],
+        };
+        let summary = survey.aggregate().unwrap();
+
+        assert_eq!(summary.mean, 4.0);
+        assert_eq!(summary.agreement, WestrumAgreement::Divided);
+        assert_eq!(summary.warnings.len(), 2);
+        assert!(WestrumSurvey { responses: vec![] }.aggregate().is_none());
+    }
+}
@@ -0,0 +1,286 @@
+//! DORA metrics from exported deployment and incident events, for teams whose
+//! deployments are not visible in git.
+
+use crate::{median, MetricsReport, Timestamp, SECONDS_PER_DAY};
+use anyhow::{bail, Context, Result};
+use crt_core::catalogue::MetricCatalogue;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentStatus {
+    #[serde(alias = "succeeded", alias = "ok")]
+    Success,
+    #[serde(alias = "failure")]
+    Failed,
+    #[serde(alias = "rollback")]
+    RolledBack,
+}
+
+/// One deployment. `committed_at` is the time of the deployed change, needed
+/// for lead time; `status` defaults to success.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DeploymentEvent {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub timestamp: Timestamp,
+    #[serde(default)]
+    pub commit: Option<String>,
+    #[serde(default)]
+    pub committed_at: Option<Timestamp>,
+    #[serde(default)]
+    pub status: Option<DeploymentStatus>,
+}
+
+impl DeploymentEvent {
+    fn is_failed(&self) -> bool {
+        matches!(self.status, Some(DeploymentStatus::Failed | DeploymentStatus::RolledBack))
+    }
+
+    /// Whether an incident's `caused_by` names this deployment, by id or commit.
+    fn is_named(&self, reference: &str) -> bool {
+        self.id.as_deref() == Some(reference) || self.commit.as_deref() == Some(reference)
+    }
+}
+
+/// One production incident. `caused_by` names the deployment (id or commit)
+/// that caused it, if known.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct IncidentEvent {
+    pub opened: Timestamp,
+    #[serde(default)]
+    pub resolved: Option<Timestamp>,
+    #[serde(default)]
+    pub caused_by: Option<String>,
+}
+
+/// What to do with extreme lead time and restore time samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlierPolicy {
+    Keep,
+    /// Drop samples beyond `factor` interquartile ranges outside the quartiles.
+    Iqr(f32),
+    /// Clamp samples to at most this many days.
+    Cap(f32),
+}
+
+impl OutlierPolicy {
+    /// Apply the policy, returning the kept samples and how many were changed or dropped.
+    pub fn apply(&self, samples: &[f32]) -> (Vec<f32>, usize) {
+        match *self {
+            OutlierPolicy::Keep => (samples.to_vec(), 0),
+            OutlierPolicy::Iqr(factor) => {
+                let mut sorted = samples.to_vec();
+                sorted.sort_by(|a, b| a.total_cmp(b));
+                let (Some(q1), Some(q3)) = (median(&sorted[..sorted.len() / 2]), median(&sorted[sorted.len().div_ceil(2)..])) else {
+                    return (sorted, 0);
+                };
+                let (low, high) = (q1 - factor * (q3 - q1), q3 + factor * (q3 - q1));
+                let kept: Vec<f32> = samples.iter().copied().filter(|s| (low..=high).contains(s)).collect();
+                let removed = samples.len() - kept.len();
+                (kept, removed)
+            }
+            OutlierPolicy::Cap(days) => {
+                let capped = samples.iter().filter(|s| **s > days).count();
+                (samples.iter().map(|s| s.min(days)).collect(), capped)
+            }
+        }
+    }
+}
+
+impl FromStr for OutlierPolicy {
+    type Err = String;
+
+    /// `keep`, `iqr` (factor 1.5), `iqr:<factor>` or `cap:<days>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, argument) = s.split_once(':').unwrap_or((s, ""));
+        let number = |default: Option<f32>| -> Result<f32, String> {
+            match (argument, default) {
+                ("", Some(default)) => Ok(default),
+                _ => argument.parse().map_err(|_| format!("Expected a number in '{s}'")),
+            }
+        };
+        match kind {
+            "keep" => Ok(OutlierPolicy::Keep),
+            "iqr" => Ok(OutlierPolicy::Iqr(number(Some(1.5))?)),
+            "cap" => Ok(OutlierPolicy::Cap(number(None)?)),
+            _ => Err(format!("Unknown outlier policy '{s}' (expected keep, iqr[:factor] or cap:<days>)")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EventOptions {
+    /// Window start and end as unix timestamps.
+    pub since: i64,
+    pub until: i64,
+    pub outliers: OutlierPolicy,
+}
+
+/// Load records from a `.csv` file (with a header row) or a JSON array.
+pub fn load_records<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(content.as_bytes())
+            .deserialize()
+            .enumerate()
+            .map(|(row, record)| record.with_context(|| format!("{}: row {}", path.display(), row + 1)))
+            .collect(),
+        _ => serde_json::from_str(&content).with_context(|| format!("Invalid JSON in {}", path.display())),
+    }
+}
+
+/// Compute deployment frequency, lead time, change failure rate and MTTR over
+/// the window. A deployment counts as failed when its status says so or an
+/// incident names it in `caused_by`.
+pub fn compute_event_metrics(
+    deployments: &[DeploymentEvent],
+    incidents: &[IncidentEvent],
+    options: &EventOptions,
+    catalogue: &MetricCatalogue,
+) -> Result<MetricsReport> {
+    if options.until <= options.since {
+        bail!("The window must end after it starts");
+    }
+    let window_days = (options.until - options.since) as f32 / SECONDS_PER_DAY;
+    let mut report = MetricsReport::new(window_days);
+    let in_window = |time: Timestamp| time.0 >= options.since && time.0 <= options.until;
+
+    let deployed: Vec<&DeploymentEvent> = deployments.iter().filter(|d| in_window(d.timestamp)).collect();
+    report.insert(catalogue, "deployment_frequency", deployed.len() as f32 / window_days, deployed.len());
+
+    let lead_times: Vec<f32> = deployed
+        .iter()
+        .filter_map(|d| Some((d.timestamp.0 - d.committed_at?.0).max(0) as f32 / SECONDS_PER_DAY))
+        .collect();
+    let (lead_times, changed) = options.outliers.apply(&lead_times);
+    if changed > 0 {
+        report.warn(format!("{changed} lead time outlier(s) adjusted"));
+    }
+    match median(&lead_times) {
+        Some(lead_time) => report.insert(catalogue, "lead_time", lead_time, lead_times.len()),
+        None => report.warn("No deployments with committed_at in the window; lead time not computed"),
+    }
+
+    if deployed.is_empty() {
+        report.warn("No deployments in the window; change failure rate not computed");
+    } else {
+        let causes: HashSet<&str> = incidents.iter().filter_map(|i| i.caused_by.as_deref()).collect();
+        let failed = deployed
+            .iter()
+            .filter(|d| d.is_failed() || causes.iter().any(|cause| d.is_named(cause)))
+            .count();
+        report.insert(catalogue, "change_failure_rate", 100.0 * failed as f32 / deployed.len() as f32, deployed.len());
+    }
+
+    let opened: Vec<&IncidentEvent> = incidents.iter().filter(|i| in_window(i.opened)).collect();
+    let unresolved = opened.iter().filter(|i| i.resolved.is_none()).count();
+    if unresolved > 0 {
+        report.warn(format!("{unresolved} unresolved incident(s) left out of MTTR"));
+    }
+    let restore_times: Vec<f32> = opened
+        .iter()
+        .filter_map(|i| Some((i.resolved?.0 - i.opened.0).max(0) as f32 / SECONDS_PER_DAY))
+        .collect();
+    let (restore_times, changed) = options.outliers.apply(&restore_times);
+    if changed > 0 {
+        report.warn(format!("{changed} restore time outlier(s) adjusted"));
+    }
+    if restore_times.is_empty() {
+        report.warn("No resolved incidents in the window; MTTR not computed");
+    } else {
+        let mean = restore_times.iter().sum::<f32>() / restore_times.len() as f32;
+        report.insert(catalogue, "mttr", mean, restore_times.len());
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400;
+    const START: i64 = 1_700_000_000;
+
+    fn deployment(day: i64, id: &str, lead_days: Option<i64>, status: Option<DeploymentStatus>) -> DeploymentEvent {
+        DeploymentEvent {
+            id: Some(id.to_string()),
+            timestamp: Timestamp(START + day * DAY),
+            commit: None,
+            committed_at: lead_days.map(|lead| Timestamp(START + (day - lead) * DAY)),
+            status,
+        }
+    }
+
+    #[test]
+    fn computes_all_four_key_metrics() {
+        let deployments = vec![
+            deployment(1, "d1", Some(1), None),
+            deployment(2, "d2", Some(2), Some(DeploymentStatus::RolledBack)),
+            deployment(3, "d3", Some(3), None),
+            deployment(4, "d4", None, None),
+        ];
+        let incidents = vec![
+            IncidentEvent {
+                opened: Timestamp(START + 3 * DAY),
+                resolved: Some(Timestamp(START + 3 * DAY + DAY / 4)),
+                caused_by: Some("d3".to_string()),
+            },
+            IncidentEvent {
+                opened: Timestamp(START + 5 * DAY),
+                resolved: None,
+                caused_by: None,
+            },
+        ];
+        let options = EventOptions {
+            since: START,
+            until: START + 8 * DAY,
+            outliers: OutlierPolicy::Keep,
+        };
+        let report = compute_event_metrics(&deployments, &incidents, &options, &MetricCatalogue::builtin()).unwrap();
+
+        assert_eq!(report.metrics["deployment_frequency"].value, 0.5);
+        assert_eq!(report.metrics["lead_time"].value, 2.0);
+        assert_eq!(report.metrics["change_failure_rate"].value, 50.0);
+        assert_eq!(report.metrics["mttr"].value, 0.25);
+        assert!(report.warnings.iter().any(|w| w.contains("unresolved")));
+    }
+
+    #[test]
+    fn outlier_policies() {
+        let samples = [1.0, 1.5, 2.0, 2.5, 3.0, 3.5, 40.0];
+        assert_eq!(OutlierPolicy::Keep.apply(&samples).1, 0);
+        assert_eq!(OutlierPolicy::Iqr(1.5).apply(&samples), (vec![1.0, 1.5, 2.0, 2.5, 3.0, 3.5], 1));
+        assert_eq!(OutlierPolicy::Cap(10.0).apply(&samples).0[6], 10.0);
+
+        assert_eq!("iqr".parse(), Ok(OutlierPolicy::Iqr(1.5)));
+        assert_eq!("cap:14".parse(), Ok(OutlierPolicy::Cap(14.0)));
+        assert!("cap".parse::<OutlierPolicy>().is_err());
+    }
+
+    #[test]
+    fn loads_csv_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deployments.csv");
+        std::fs::write(
+            &path,
+            "id,timestamp,commit,committed_at,status\n\
+             d1,2024-03-01T10:00:00Z,abc123,2024-02-29 10:00:00,success\n\
+             d2,1709370000,def456,,failed\n",
+        )
+        .unwrap();
+
+        let deployments: Vec<DeploymentEvent> = load_records(&path).unwrap();
+        assert_eq!(deployments.len(), 2);
+        assert_eq!(deployments[0].timestamp.0 - deployments[0].committed_at.unwrap().0, DAY);
+        assert_eq!(deployments[1].committed_at, None);
+        assert!(deployments[1].is_failed());
+    }
+}
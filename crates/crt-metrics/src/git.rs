@@ -0,0 +1,283 @@
+//! DORA and engineering metrics from a local git repository, read through the
+//! `git` command line.
+
+use crate::{median, MetricsReport, SECONDS_PER_DAY};
+use anyhow::{bail, Context, Result};
+use crt_core::catalogue::MetricCatalogue;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How deployments show up in the repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeploymentSource {
+    /// Every tag matching a `git for-each-ref` pattern such as `deploy-*` is a
+    /// deployment of the tagged commit, at the tag's creation time.
+    Tags(String),
+    /// Every first-parent commit on this branch (e.g. `production`) is a
+    /// deployment, at its commit time.
+    Branch(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct GitOptions {
+    pub repo: PathBuf,
+    pub deployments: DeploymentSource,
+    /// Branch that feature branches merge into, used for commit frequency and
+    /// branch lifetime.
+    pub main_branch: String,
+    /// Window start and end as unix timestamps.
+    pub since: i64,
+    pub until: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Deployment {
+    commit: String,
+    time: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Commit {
+    hash: String,
+    time: i64,
+    author: String,
+    parents: Vec<String>,
+}
+
+/// Compute deployment frequency, lead time, commit frequency per developer and
+/// branch lifetime over the window. Metrics without samples are left out and
+/// reported under `warnings`.
+pub fn compute_git_metrics(options: &GitOptions, catalogue: &MetricCatalogue) -> Result<MetricsReport> {
+    if options.until <= options.since {
+        bail!("The window must end after it starts");
+    }
+    let window_days = (options.until - options.since) as f32 / SECONDS_PER_DAY;
+    let mut report = MetricsReport::new(window_days);
+    let in_window = |time: i64| time >= options.since && time <= options.until;
+
+    let deployments = list_deployments(&options.repo, &options.deployments)?;
+    let deployed: Vec<usize> = (0..deployments.len()).filter(|i| in_window(deployments[*i].time)).collect();
+    report.insert(catalogue, "deployment_frequency", deployed.len() as f32 / window_days, deployed.len());
+
+    let mut lead_times = Vec::new();
+    for index in deployed {
+        let deployment = &deployments[index];
+        let range = match index.checked_sub(1).map(|previous| &deployments[previous]) {
+            Some(previous) => format!("{}..{}", previous.commit, deployment.commit),
+            None => deployment.commit.clone(),
+        };
+        for commit in log(&options.repo, &["--no-merges", &range])? {
+            // without an earlier deployment, only count changes made in the window
+            if index == 0 && !in_window(commit.time) {
+                continue;
+            }
+            lead_times.push((deployment.time - commit.time).max(0) as f32 / SECONDS_PER_DAY);
+        }
+    }
+    match median(&lead_times) {
+        Some(lead_time) => report.insert(catalogue, "lead_time", lead_time, lead_times.len()),
+        None => report.warn("No deployed commits in the window; lead time not computed"),
+    }
+
+    let mainline: Vec<Commit> = log(&options.repo, &[&options.main_branch])?
+        .into_iter()
+        .filter(|commit| in_window(commit.time))
+        .collect();
+    let authored: Vec<&Commit> = mainline.iter().filter(|commit| commit.parents.len() <= 1).collect();
+    let authors: HashSet<&str> = authored.iter().map(|commit| commit.author.as_str()).collect();
+    if authors.is_empty() {
+        report.warn(format!("No commits on {} in the window; commit frequency not computed", options.main_branch));
+    } else {
+        let per_developer = authored.len() as f32 / authors.len() as f32 / window_days;
+        report.insert(catalogue, "commit_frequency", per_developer, authored.len());
+    }
+
+    let mut lifetimes = Vec::new();
+    let merges = log(&options.repo, &["--merges", "--first-parent", &options.main_branch])?;
+    for merge in merges.iter().filter(|merge| in_window(merge.time)) {
+        let range = format!("{}..{}", merge.parents[0], merge.parents[1]);
+        let first = log(&options.repo, &[&range])?.iter().map(|commit| commit.time).min();
+        if let Some(first) = first {
+            lifetimes.push((merge.time - first).max(0) as f32 / SECONDS_PER_DAY);
+        }
+    }
+    match median(&lifetimes) {
+        Some(lifetime) => report.insert(catalogue, "branch_lifetime", lifetime, lifetimes.len()),
+        None => report.warn(format!("No merges into {} in the window; branch lifetime not computed", options.main_branch)),
+    }
+
+    Ok(report)
+}
+
+/// Author time of `commit`, for deployment exports that name commits but not
+/// when they were made.
+pub fn commit_time(repo: &Path, commit: &str) -> Result<i64> {
+    let output = git(repo, &["show", "-s", "--format=%at", commit])?;
+    output.trim().parse().with_context(|| format!("Bad commit time for {commit}"))
+}
+
+fn git(repo: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Commits reachable from `args`, newest first, timed by author date.
+fn log(repo: &Path, args: &[&str]) -> Result<Vec<Commit>> {
+    let mut command = vec!["log", "--format=%H%x09%at%x09%ae%x09%P"];
+    command.extend_from_slice(args);
+    git(repo, &command)?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let mut fields = line.split('\t');
+            let hash = fields.next().unwrap_or_default().to_string();
+            let time = fields.next().unwrap_or_default().parse().with_context(|| format!("Bad git log line: {line}"))?;
+            let author = fields.next().unwrap_or_default().to_lowercase();
+            let parents = fields.next().unwrap_or_default().split_whitespace().map(str::to_string).collect();
+            Ok(Commit { hash, time, author, parents })
+        })
+        .collect()
+}
+
+/// Deployments oldest first.
+fn list_deployments(repo: &Path, source: &DeploymentSource) -> Result<Vec<Deployment>> {
+    let mut deployments = match source {
+        DeploymentSource::Tags(pattern) => {
+            let pattern = format!("refs/tags/{pattern}");
+            let refs = git(
+                repo,
+                &["for-each-ref", "--format=%(creatordate:unix)%09%(objectname)%09%(*objectname)", &pattern],
+            )?;
+            refs.lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| {
+                    let fields: Vec<&str> = line.split('\t').collect();
+                    let time = fields[0].parse().with_context(|| format!("Bad tag line: {line}"))?;
+                    // annotated tags point at a tag object; use the commit it peels to
+                    let commit = match fields.get(2) {
+                        Some(peeled) if !peeled.is_empty() => peeled.to_string(),
+                        _ => fields.get(1).copied().unwrap_or_default().to_string(),
+                    };
+                    Ok(Deployment { commit, time })
+                })
+                .collect::<Result<Vec<_>>>()?
+        }
+        DeploymentSource::Branch(branch) => {
+            let commits = git(repo, &["log", "--first-parent", "--format=%H%x09%ct", branch])?;
+            commits
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| {
+                    let (commit, time) = line.split_once('\t').unwrap_or((line, ""));
+                    let time = time.parse().with_context(|| format!("Bad git log line: {line}"))?;
+                    Ok(Deployment {
+                        commit: commit.to_string(),
+                        time,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?
+        }
+    };
+    deployments.sort_by_key(|deployment| deployment.time);
+    Ok(deployments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400;
+    const START: i64 = 1_700_000_000;
+
+    struct Repo(tempfile::TempDir);
+
+    impl Repo {
+        fn new() -> Self {
+            let repo = Repo(tempfile::tempdir().unwrap());
+            repo.run(&["init", "-q", "-b", "main"], START);
+            repo
+        }
+
+        fn run(&self, args: &[&str], time: i64) {
+            let date = format!("@{time} +0000");
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(self.0.path())
+                .args(["-c", "user.name=Dev", "-c", "user.email=dev@example.com", "-c", "tag.gpgSign=false", "-c", "commit.gpgSign=false"])
+                .args(args)
+                .env("GIT_AUTHOR_DATE", &date)
+                .env("GIT_COMMITTER_DATE", &date)
+                .status()
+                .unwrap();
+            assert!(status.success(), "git {args:?}");
+        }
+
+        fn commit(&self, message: &str, time: i64) {
+            self.run(&["commit", "-q", "--allow-empty", "-m", message], time);
+        }
+    }
+
+    #[test]
+    fn computes_metrics_from_tags_and_merges() {
+        let repo = Repo::new();
+        repo.commit("initial", START);
+        repo.run(&["tag", "deploy-1"], START);
+
+        // a feature branch living two days, merged and deployed a day later
+        repo.run(&["checkout", "-q", "-b", "feature"], START + DAY);
+        repo.commit("feature work", START + DAY);
+        repo.commit("more work", START + 2 * DAY);
+        repo.run(&["checkout", "-q", "main"], START + 3 * DAY);
+        repo.run(&["merge", "-q", "--no-ff", "-m", "merge feature", "feature"], START + 3 * DAY);
+        repo.run(&["tag", "-a", "deploy-2", "-m", "release"], START + 4 * DAY);
+
+        let options = GitOptions {
+            repo: repo.0.path().to_path_buf(),
+            deployments: DeploymentSource::Tags("deploy-*".to_string()),
+            main_branch: "main".to_string(),
+            since: START + DAY / 2,
+            until: START + 10 * DAY,
+        };
+        let report = compute_git_metrics(&options, &MetricCatalogue::builtin()).unwrap();
+
+        let deployment_frequency = &report.metrics["deployment_frequency"];
+        assert_eq!(deployment_frequency.samples, 1);
+        assert!((deployment_frequency.value - 1.0 / 9.5).abs() < 1e-4);
+
+        // changes waited 3 and 2 days for deploy-2
+        let lead_time = &report.metrics["lead_time"];
+        assert_eq!(lead_time.samples, 2);
+        assert_eq!(lead_time.value, 2.5);
+
+        assert_eq!(report.metrics["branch_lifetime"].value, 2.0);
+        assert_eq!(report.metrics["commit_frequency"].samples, 2);
+    }
+
+    #[test]
+    fn warns_when_window_is_empty() {
+        let repo = Repo::new();
+        repo.commit("initial", START);
+
+        let options = GitOptions {
+            repo: repo.0.path().to_path_buf(),
+            deployments: DeploymentSource::Branch("main".to_string()),
+            main_branch: "main".to_string(),
+            since: START + DAY,
+            until: START + 2 * DAY,
+        };
+        let report = compute_git_metrics(&options, &MetricCatalogue::builtin()).unwrap();
+
+        assert!(!report.metrics.contains_key("lead_time"));
+        assert!(!report.metrics.contains_key("branch_lifetime"));
+        assert!(report.warnings.iter().any(|w| w.contains("lead time")));
+    }
+}
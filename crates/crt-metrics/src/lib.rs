@@ -0,0 +1,268 @@
+//! Compute metric values from delivery data instead of guessing slider positions.
+
+pub mod events;
+pub mod git;
+pub mod work_items;
+
+use crt_core::catalogue::MetricCatalogue;
+use crt_core::types::{DoraMetric, DoraMetrics, EngineeringMetrics};
+use crt_core::validation::{is_fixed_metric, FIXED_METRICS};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub const SECONDS_PER_DAY: f32 = 86_400.0;
+
+/// A point in time as unix seconds. Deserializes from a number, a numeric
+/// string, RFC 3339 (`2024-03-01T10:00:00Z`), `YYYY-MM-DD HH:MM:SS` or
+/// `YYYY-MM-DD`; times without an offset are taken as UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Timestamp(pub i64);
+
+impl std::str::FromStr for Timestamp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(seconds) = s.parse::<i64>() {
+            return Ok(Timestamp(seconds));
+        }
+        if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+            return Ok(Timestamp(time.timestamp()));
+        }
+        for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+            if let Ok(time) = NaiveDateTime::parse_from_str(s, format) {
+                return Ok(Timestamp(time.and_utc().timestamp()));
+            }
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Ok(Timestamp(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp()));
+        }
+        Err(format!("Unrecognised timestamp '{s}'"))
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TimestampVisitor;
+
+        impl de::Visitor<'_> for TimestampVisitor {
+            type Value = Timestamp;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("unix seconds or a date-time string")
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Timestamp, E> {
+                Ok(Timestamp(value))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Timestamp, E> {
+                i64::try_from(value).map(Timestamp).map_err(E::custom)
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<Timestamp, E> {
+                Ok(Timestamp(value as i64))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Timestamp, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(TimestampVisitor)
+    }
+}
+
+/// A computed metric value in its catalogue unit, with the slider position it
+/// maps to and the number of samples it was computed from.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComputedMetric {
+    pub value: f32,
+    pub unit: String,
+    pub slider: f32,
+    pub samples: usize,
+}
+
+/// The slider position of a request field no computed value fills.
+pub const UNMEASURED_SLIDER: f32 = 0.5;
+
+/// The metric fields of an analyse request, to merge into one.
+#[derive(Debug, Serialize)]
+pub struct RequestSliders {
+    pub dora_metrics: DoraMetrics,
+    pub extended_engineering_metrics: EngineeringMetrics,
+    /// Other catalogue metrics, for the request's `metrics` map.
+    pub metrics: BTreeMap<String, f32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MetricsReport {
+    pub window_days: f32,
+    pub metrics: BTreeMap<String, ComputedMetric>,
+    pub warnings: Vec<String>,
+}
+
+impl MetricsReport {
+    pub fn new(window_days: f32) -> Self {
+        MetricsReport {
+            window_days,
+            ..Default::default()
+        }
+    }
+
+    /// Record `value` (already in the catalogue unit of `name`), warning when
+    /// the metric is unknown or the value falls outside the slider range.
+    pub fn insert(&mut self, catalogue: &MetricCatalogue, name: &str, value: f32, samples: usize) {
+        let Some(config) = catalogue.get(name) else {
+            self.warnings.push(format!("{name} is not in the metric catalogue"));
+            return;
+        };
+        if value < config.min_value || value > config.max_value {
+            self.warnings.push(format!(
+                "{name} of {value} {} is outside {}..{}; the slider is clamped",
+                config.unit, config.min_value, config.max_value
+            ));
+        }
+        self.metrics.insert(
+            name.to_string(),
+            ComputedMetric {
+                value,
+                unit: config.unit.to_string(),
+                slider: config.to_slider(value),
+                samples,
+            },
+        );
+    }
+
+    pub fn warn(&mut self, warning: impl Into<String>) {
+        self.warnings.push(warning.into());
+    }
+
+    pub fn value(&self, name: &str) -> Option<DoraMetric> {
+        self.metrics.get(name).map(|metric| DoraMetric {
+            value: metric.value,
+            unit: metric.unit.clone(),
+        })
+    }
+
+    /// Slider positions laid out as the metric fields of an analyse request.
+    /// DORA and engineering metrics not computed here sit at
+    /// `UNMEASURED_SLIDER`; see `unmeasured`.
+    pub fn sliders(&self) -> RequestSliders {
+        let mut dora_metrics = DoraMetrics {
+            deployment_frequency: UNMEASURED_SLIDER,
+            lead_time: UNMEASURED_SLIDER,
+            change_failure_rate: UNMEASURED_SLIDER,
+            mttr: UNMEASURED_SLIDER,
+        };
+        let mut extended_engineering_metrics = EngineeringMetrics {
+            commit_frequency: UNMEASURED_SLIDER,
+            branch_lifetime: UNMEASURED_SLIDER,
+            pbis_delivered_per_sprint_per_team: UNMEASURED_SLIDER,
+        };
+        self.apply_to_dora(&mut dora_metrics);
+        self.apply_to_engineering(&mut extended_engineering_metrics);
+        RequestSliders {
+            dora_metrics,
+            extended_engineering_metrics,
+            metrics: self
+                .metrics
+                .iter()
+                .filter(|(name, _)| !is_fixed_metric(name))
+                .map(|(name, metric)| (name.clone(), metric.slider))
+                .collect(),
+        }
+    }
+
+    /// DORA and engineering metrics a request needs that were not computed.
+    pub fn unmeasured(&self) -> Vec<&'static str> {
+        FIXED_METRICS.into_iter().filter(|name| !self.metrics.contains_key(*name)).collect()
+    }
+
+    /// Overwrite the sliders of `dora` for every DORA metric computed here.
+    pub fn apply_to_dora(&self, dora: &mut DoraMetrics) {
+        let fields = [
+            ("deployment_frequency", &mut dora.deployment_frequency),
+            ("lead_time", &mut dora.lead_time),
+            ("change_failure_rate", &mut dora.change_failure_rate),
+            ("mttr", &mut dora.mttr),
+        ];
+        for (name, field) in fields {
+            if let Some(metric) = self.metrics.get(name) {
+                *field = metric.slider;
+            }
+        }
+    }
+
+    /// Overwrite the sliders of `engineering` for every engineering metric computed here.
+    pub fn apply_to_engineering(&self, engineering: &mut EngineeringMetrics) {
+        let fields = [
+            ("commit_frequency", &mut engineering.commit_frequency),
+            ("branch_lifetime", &mut engineering.branch_lifetime),
+            ("pbis_delivered_per_sprint_per_team", &mut engineering.pbis_delivered_per_sprint_per_team),
+        ];
+        for (name, field) in fields {
+            if let Some(metric) = self.metrics.get(name) {
+                *field = metric.slider;
+            }
+        }
+    }
+}
+
+/// Median of a sample, or `None` when it is empty.
+pub fn median(values: &[f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+    Some(if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crt_core::types::AnalyseRequest;
+    use crt_core::validation::{Validate, ValidationContext};
+    use std::sync::Arc;
+
+    #[test]
+    fn sliders_fill_a_valid_request() {
+        let catalogue = MetricCatalogue::from_toml(
+            r#"
+            [[metrics]]
+            name = "rework_rate"
+            unit = "%"
+            min_value = 0
+            max_value = 100
+            "#,
+        )
+        .unwrap();
+        let mut report = MetricsReport::new(30.0);
+        report.insert(&catalogue, "lead_time", 7.0, 12);
+        report.insert(&catalogue, "branch_lifetime", 2.0, 5);
+        report.insert(&catalogue, "rework_rate", 10.0, 40);
+        assert_eq!(report.unmeasured().len(), 5);
+
+        let mut request = serde_json::to_value(report.sliders()).unwrap();
+        assert_eq!(request["dora_metrics"]["lead_time"], 0.5);
+        assert_eq!(request["dora_metrics"]["mttr"], UNMEASURED_SLIDER);
+        assert_eq!(request["metrics"].as_object().unwrap().len(), 1);
+
+        request["crt"] = "Entities\nE1. First\nE2. Second\n\nLinks\nL1. E1 → E2\n".into();
+        request["time_allocation"] = serde_json::json!({"meetings": 20, "unplanned": 20, "bugs": 20, "feature": 20, "tech_debt": 20});
+        let request: AnalyseRequest = serde_json::from_value(request).unwrap();
+        let context = ValidationContext {
+            catalogue: Arc::new(catalogue),
+            ..Default::default()
+        };
+        request.validate_all_with(&context).unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+//! `TimeAllocation` from Jira or Azure Boards work-item exports, using a rule
+//! file that maps issue types and labels onto the five time buckets.
+
+use anyhow::{bail, Context, Result};
+use crt_core::types::TimeAllocation;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Which export columns to read. Jira repeats the `Labels` column once per
+/// label; every column with the labels header is read.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ColumnConfig {
+    #[serde(default = "default_issue_type_column")]
+    pub issue_type: String,
+    #[serde(default = "default_labels_column")]
+    pub labels: String,
+    #[serde(default = "default_time_column")]
+    pub time: String,
+    /// Unit of the time column: `seconds` (Jira), `minutes`, `hours` (Azure Boards) or `days`.
+    #[serde(default = "default_time_unit")]
+    pub time_unit: String,
+    /// Separator between labels within one cell.
+    #[serde(default = "default_label_separator")]
+    pub label_separator: String,
+}
+
+fn default_issue_type_column() -> String {
+    "Issue Type".to_string()
+}
+
+fn default_labels_column() -> String {
+    "Labels".to_string()
+}
+
+fn default_time_column() -> String {
+    "Time Spent".to_string()
+}
+
+fn default_time_unit() -> String {
+    "seconds".to_string()
+}
+
+fn default_label_separator() -> String {
+    ";".to_string()
+}
+
+impl Default for ColumnConfig {
+    fn default() -> Self {
+        ColumnConfig {
+            issue_type: default_issue_type_column(),
+            labels: default_labels_column(),
+            time: default_time_column(),
+            time_unit: default_time_unit(),
+            label_separator: default_label_separator(),
+        }
+    }
+}
+
+/// Items whose issue type or any label is listed go to `bucket`. Matching is
+/// case-insensitive.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AllocationRule {
+    pub bucket: String,
+    #[serde(default)]
+    pub issue_types: Vec<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+impl AllocationRule {
+    fn matches(&self, item: &WorkItem) -> bool {
+        let listed = |list: &[String], value: &str| list.iter().any(|entry| entry.eq_ignore_ascii_case(value));
+        listed(&self.issue_types, &item.issue_type) || item.labels.iter().any(|label| listed(&self.labels, label))
+    }
+}
+
+/// Rule file contents. Rules are tried in order and the first match wins;
+/// unmatched items go to `default_bucket`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AllocationRules {
+    #[serde(default)]
+    pub columns: ColumnConfig,
+    pub default_bucket: String,
+    pub rules: Vec<AllocationRule>,
+}
+
+impl AllocationRules {
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let rules: AllocationRules = toml::from_str(content).context("Invalid allocation rules")?;
+        for bucket in std::iter::once(&rules.default_bucket).chain(rules.rules.iter().map(|rule| &rule.bucket)) {
+            if !TimeAllocation::BUCKETS.contains(&bucket.as_str()) {
+                bail!("Unknown bucket '{bucket}' (expected one of {})", TimeAllocation::BUCKETS.join(", "));
+            }
+        }
+        seconds_per_unit(&rules.columns.time_unit)?;
+        Ok(rules)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+        Self::from_toml(&content)
+    }
+
+    pub fn bucket_for(&self, item: &WorkItem) -> Option<&str> {
+        self.rules.iter().find(|rule| rule.matches(item)).map(|rule| rule.bucket.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkItem {
+    pub issue_type: String,
+    pub labels: Vec<String>,
+    pub hours: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AllocationReport {
+    pub time_allocation: TimeAllocation,
+    pub hours: BTreeMap<String, f32>,
+    pub items: usize,
+    /// Items no rule matched, counted under the default bucket.
+    pub unmatched: usize,
+}
+
+fn seconds_per_unit(unit: &str) -> Result<f32> {
+    Ok(match unit {
+        "seconds" => 1.0,
+        "minutes" => 60.0,
+        "hours" => 3600.0,
+        "days" => 86_400.0,
+        _ => bail!("Unknown time unit '{unit}' (expected seconds, minutes, hours or days)"),
+    })
+}
+
+/// Read work items from a CSV export with a header row. Rows without logged
+/// time count as zero hours.
+pub fn read_work_items(content: &str, columns: &ColumnConfig) -> Result<Vec<WorkItem>> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(content.as_bytes());
+    let headers = reader.headers()?.clone();
+    let find = |name: &str| headers.iter().position(|header| header == name);
+    let issue_type = find(&columns.issue_type).with_context(|| format!("No '{}' column", columns.issue_type))?;
+    let time = find(&columns.time).with_context(|| format!("No '{}' column", columns.time))?;
+    let labels: Vec<usize> = (0..headers.len()).filter(|i| &headers[*i] == columns.labels.as_str()).collect();
+    let unit = seconds_per_unit(&columns.time_unit)?;
+
+    let mut items = Vec::new();
+    for (row, record) in reader.records().enumerate() {
+        let record = record.with_context(|| format!("Row {}", row + 1))?;
+        let cell = |index: usize| record.get(index).unwrap_or_default();
+        let logged = match cell(time) {
+            "" => 0.0,
+            value => value.parse::<f32>().with_context(|| format!("Row {}: bad time '{value}'", row + 1))?,
+        };
+        items.push(WorkItem {
+            issue_type: cell(issue_type).to_string(),
+            labels: labels
+                .iter()
+                .flat_map(|index| cell(*index).split(columns.label_separator.as_str()))
+                .map(str::trim)
+                .filter(|label| !label.is_empty())
+                .map(str::to_string)
+                .collect(),
+            hours: logged * unit / 3600.0,
+        });
+    }
+    Ok(items)
+}
+
+/// Sum hours per bucket and normalise them into whole percentages.
+pub fn allocate(items: &[WorkItem], rules: &AllocationRules) -> Result<AllocationReport> {
+    let mut hours: BTreeMap<String, f32> = TimeAllocation::BUCKETS.iter().map(|bucket| (bucket.to_string(), 0.0)).collect();
+    let mut unmatched = 0;
+    for item in items {
+        let bucket = rules.bucket_for(item).unwrap_or_else(|| {
+            unmatched += 1;
+            &rules.default_bucket
+        });
+        *hours.entry(bucket.to_string()).or_default() += item.hours;
+    }
+
+    let weights = TimeAllocation::BUCKETS.map(|bucket| hours[bucket]);
+    let Some(time_allocation) = TimeAllocation::from_weights(weights) else {
+        bail!("No time logged on any work item");
+    };
+    Ok(AllocationReport {
+        time_allocation,
+        hours,
+        items: items.len(),
+        unmatched,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+        default_bucket = "feature"
+
+        [[rules]]
+        bucket = "unplanned"
+        issue_types = ["Incident"]
+        labels = ["support"]
+
+        [[rules]]
+        bucket = "bugs"
+        issue_types = ["Bug"]
+
+        [[rules]]
+        bucket = "tech_debt"
+        labels = ["tech-debt"]
+
+        [[rules]]
+        bucket = "meetings"
+        issue_types = ["Meeting"]
+    "#;
+
+    #[test]
+    fn maps_jira_export_to_allocation() {
+        let rules = AllocationRules::from_toml(RULES).unwrap();
+        let export = "\
+Issue key,Issue Type,Labels,Labels,Time Spent
+A-1,Story,,,36000
+A-2,Bug,support,,7200
+A-3,Story,ui,tech-debt,10800
+A-4,Meeting,,,3600
+A-5,bug,,,3600
+A-6,Task,,,
+";
+        let items = read_work_items(export, &rules.columns).unwrap();
+        assert_eq!(items[2].labels, vec!["ui", "tech-debt"]);
+
+        let report = allocate(&items, &rules).unwrap();
+        assert_eq!(report.hours["unplanned"], 2.0);
+        assert_eq!(report.hours["bugs"], 1.0);
+        assert_eq!(report.unmatched, 2);
+        // 1 : 2 : 1 : 10 : 3 hours, largest remainders round up
+        assert_eq!(
+            report.time_allocation,
+            TimeAllocation {
+                meetings: 6,
+                unplanned: 12,
+                bugs: 6,
+                feature: 59,
+                tech_debt: 17,
+            }
+        );
+        assert_eq!(report.time_allocation.total(), 100);
+    }
+
+    #[test]
+    fn largest_remainder_sums_to_100() {
+        let thirds = TimeAllocation::from_weights([1.0, 1.0, 1.0, 0.0, 0.0]).unwrap();
+        assert_eq!(thirds.total(), 100);
+        assert_eq!((thirds.meetings, thirds.unplanned, thirds.bugs), (34, 33, 33));
+        assert!(TimeAllocation::from_weights([0.0; 5]).is_none());
+    }
+
+    #[test]
+    fn rejects_unknown_buckets() {
+        let error = AllocationRules::from_toml("default_bucket = \"admin\"\nrules = []").unwrap_err();
+        assert!(error.to_string().contains("admin"));
+    }
+}